@@ -0,0 +1,8651 @@
+// --- Dice ---
+
+// return the expected_value of a die roll with the given number of faces
+pub const fn die_ev(faces: u32) -> f64 {
+    (1.0 + faces as f64) / 2.0
+}
+
+// name the expected value of a die with the given number of faces, e.g. d6
+macro_rules! expected_value {
+    ($n:literal) => {
+        paste::item! {
+            #[allow(non_upper_case_globals)]
+            pub const [<d $n>]:f64 = die_ev($n);
+        }
+    };
+}
+
+expected_value!(4);
+expected_value!(6);
+expected_value!(8);
+expected_value!(10);
+expected_value!(12);
+
+// expected value of a die that rerolls a 1 or 2 once, keeping the reroll
+pub fn great_weapon_fighting_value(faces: i32) -> f64 {
+    let average = die_ev(faces as u32);
+    let kept: i32 = (3..=faces).sum();
+
+    (kept as f64 + 2.0 * average) / faces as f64
+}
+
+// expected value of the higher of two rolls of count dice with the faces
+pub fn savage_attacker_value(count: i32, faces: i32) -> f64 {
+    // chance of each total, by convolving one die at a time
+    let mut pmf = vec![1.0];
+    for _ in 0..count {
+        let mut next = vec![0.0; pmf.len() + faces as usize];
+        for (total, p) in pmf.iter().enumerate() {
+            for face in 1..=faces as usize {
+                next[total + face] += p / faces as f64;
+            }
+        }
+        pmf = next;
+    }
+
+    let mut below = 0.0;
+    let mut value = 0.0;
+    for (total, p) in pmf.iter().enumerate() {
+        let at_most = below + p;
+        value += total as f64 * (at_most * at_most - below * below);
+        below = at_most;
+    }
+
+    value
+}
+
+// --- Traits ---
+
+pub trait ExpectedDamage {
+    fn expected_damage_with(&self, ac: i32, roll: Roll) -> f64;
+
+    // the same attacks, keeping only the damage of the type
+    fn of_type(&self, kind: DamageType) -> Self
+    where
+        Self: Sized;
+
+    fn expected_damage(&self, ac: i32) -> f64 {
+        self.expected_damage_with(ac, Roll::Normal)
+    }
+
+    // expected secondary effect (e.g. healing) worth a fraction of damage dealt;
+    // non-qualifying damage should be left out of what this is called on
+    fn on_hit_fraction(&self, ac: i32, fraction: f64) -> f64 {
+        fraction * self.expected_damage(ac)
+    }
+
+    // expected damage against an AC drawn from (ac, weight) pairs. 0.0 when
+    // there are no ACs or the weights sum to 0
+    fn expected_damage_weighted(&self, acs: &[(i32, f64)]) -> f64 {
+        let total: f64 = acs.iter().map(|(_, w)| w).sum();
+        if total == 0.0 {
+            return 0.0;
+        }
+
+        acs.iter()
+            .map(|&(ac, w)| w * self.expected_damage(ac))
+            .sum::<f64>()
+            / total
+    }
+
+    // expected damage summed over targets of the given ACs, each taking all
+    // of it, as with a cleave. cap a cleave by passing the targets it reaches
+    fn expected_damage_multi(&self, targets: &[i32]) -> f64 {
+        targets.iter().map(|&ac| self.expected_damage(ac)).sum()
+    }
+
+    // expected damage against a target whose defenses scale each damage
+    // type, including crit dice, by its multiplier
+    fn expected_damage_vs(&self, ac: i32, defenses: &Defenses) -> f64
+    where
+        Self: Sized,
+    {
+        defenses
+            .kinds()
+            .iter()
+            .map(|&k| (defenses.multiplier(k) - 1.0) * self.of_type(k).expected_damage(ac))
+            .sum::<f64>()
+            + self.expected_damage(ac)
+    }
+
+    // expected damage against a typical monster of the challenge rating
+    fn expected_vs_cr(&self, cr: f64) -> f64 {
+        self.expected_damage_weighted(&cr_ac_distribution(cr))
+    }
+}
+
+// picks which resource pool, if any, to spend on a round
+pub trait SpendPolicy {
+    fn choose(&mut self, resources: &Resources, ac: i32, round: u32) -> Option<usize>;
+}
+
+// --- Types ---
+
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Damage {
+    pub dmg: f64,
+    // not multiplied on crit
+    pub fixed: i32,
+    // how much of dmg and fixed is of each DamageType, the rest is untyped
+    pub typed: [(f64, i32); DAMAGE_TYPES],
+    // how many of each of DIE_FACES went into dmg, when built from dice
+    pub dice: [u32; DIE_FACES.len()],
+    // how many of dice reroll a 1 or 2 once, keeping the reroll, as with
+    // Great Weapon Fighting
+    pub rerolled: [u32; DIE_FACES.len()],
+    // Savage Attacker as (count, faces), the gain in dmg from rolling that
+    // many dice twice and keeping the higher. when they aren't among dice
+    // the gain is rolled apart from them
+    pub savage: Option<(u32, u32)>,
+}
+
+pub const DAMAGE_TYPES: usize = 13;
+
+// the dice sizes Damage keeps counts of
+pub const DIE_FACES: [u32; 6] = [4, 6, 8, 10, 12, 20];
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DamageType {
+    Acid,
+    Bludgeoning,
+    Cold,
+    Fire,
+    Force,
+    Lightning,
+    Necrotic,
+    Piercing,
+    Poison,
+    Psychic,
+    Radiant,
+    Slashing,
+    Thunder,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Attack {
+    // bonus to hit chance
+    pub hit: i32,
+
+    pub dmg: Damage,
+    // added on a crit only, its dice doubled like the weapon's
+    pub crit: Damage,
+    // added on a crit without doubling, e.g. Brutal Critical's extra dice
+    pub crit_extra: Damage,
+    // rolled in place of dmg on a crit, e.g. a d8 weapon's dice rolled as
+    // d12s, multiplied like dmg would be
+    pub dmg_on_crit: Option<Damage>,
+
+    // added only to the first copy of this attack to land in a turn
+    pub first_hit_bonus: Damage,
+
+    // damage per point the attack roll beats AC, not doubled on a crit
+    pub degrees: Option<f64>,
+
+    // floor for the damage of a hit, crit dice still add on top
+    pub min_hit_damage: Option<f64>,
+
+    // extra effect on the natural 20 itself, whatever the crit range
+    pub nat20_rider: Option<Damage>,
+
+    // lowest natural roll that crits
+    pub crit_range: i32,
+
+    // faces of a die added to the attack roll, e.g. Bless's d4
+    pub hit_die: Option<i32>,
+
+    // how many times the dice are rolled on a crit
+    pub crit_multiplier: i32,
+
+    // a natural 1 on each d20 is rerolled once, as with halfling luck
+    pub lucky: bool,
+}
+
+// chainable construction of an Attack, dice as (count, faces)
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
+pub struct AttackBuilder {
+    atk: Attack,
+}
+
+#[derive(Default, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Turn {
+    pub action: Vec<Attack>,
+    pub bonus_action: Vec<Attack>,
+    // each made on another creature's turn, so the once per turn riders can
+    // land on it again, but it doesn't make the triggered attacks
+    pub reaction: Vec<Attack>,
+    // chance a reaction comes up each round, always when None
+    pub reaction_chance: Option<f64>,
+    // attacks at another creature, against its own AC. they land none of
+    // the riders and don't make the triggered attacks
+    pub other_targets: Vec<(i32, Attack)>,
+    // an attack at another creature with its AC, made once a copy of the
+    // first action attack lands, as with Cleave. like other_targets it lands
+    // none of the riders
+    pub cleave: Option<(i32, Attack)>,
+
+    // always stacks with riders
+    pub once_on_hit: Damage,
+    pub riders: Vec<OncePerTurnRider>,
+    // extra attacks made only when their trigger happens during the turn
+    pub triggered: Vec<(Trigger, Attack)>,
+    // save spells, which don't land riders
+    pub saves: Vec<SaveAttack>,
+    pub name: String,
+}
+
+// damage the target takes when it fails a saving throw
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SaveAttack {
+    pub dc: i32,
+    pub save_bonus_of_target: i32,
+    pub dmg: Damage,
+    // most area spells still deal half on a save
+    pub half_on_save: bool,
+    // the target halves a failed save and takes nothing on a success
+    pub evasion: bool,
+}
+
+// how many d20s are rolled for an attack, and which is kept
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
+pub enum Roll {
+    #[default]
+    Normal,
+    // roll two, keep the higher
+    Advantage,
+    // roll two, keep the lower
+    Disadvantage,
+    // advantage with one die rerolled, so the best of three
+    ElvenAccuracy,
+}
+
+// condition under which a triggered attack is made
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Trigger {
+    // any action or bonus action attack crits
+    Crit,
+    // any action or bonus action attack hits
+    Hit,
+    // every action attack misses
+    ActionsMiss,
+}
+
+// 2024 weapon masteries that add attacks to a turn
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum WeaponMastery {
+    // the light weapon's extra attack is part of the action, freeing the bonus action
+    Nick,
+    // once per turn, a hit allows an attack on a second target of the AC,
+    // without the ability modifier to damage
+    Cleave(i32),
+}
+
+// how every die a turn rolls for damage is rolled
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
+pub enum DicePolicy {
+    // as the dice are recorded
+    #[default]
+    Standard,
+    // each die rerolls a 1 or 2 once, as with Great Weapon Fighting
+    GreatWeaponFighting,
+    // the first action attack's biggest dice rolled twice on the first
+    // hit, keeping the higher, as with Savage Attacker
+    SavageAttacker,
+}
+
+// damage added to the first hit of the turn, e.g. Sneak Attack
+#[derive(Default, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OncePerTurnRider {
+    pub name: String,
+    pub dmg: Damage,
+    pub stacking: Stacking,
+    // held for the first crit instead of the first hit
+    pub crit_only: bool,
+}
+
+#[derive(Default, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Stacking {
+    #[default]
+    Stackable,
+    // only the most damaging rider of the named group applies
+    Exclusive(String),
+}
+
+pub struct HuntersMark {
+    pub unmodified: Turn,
+    pub first_turn: Turn,
+    pub max_damage: Turn,
+}
+
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
+pub struct Monster {
+    pub ac: i32,
+    pub hp: i32,
+}
+
+// a limited use feature, e.g. spell slots of one level or ki, and the turn
+// spending a use of it buys
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Pool {
+    pub name: String,
+    pub uses: u32,
+    pub turn: Turn,
+}
+
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Resources {
+    pub pools: Vec<Pool>,
+}
+
+// spends the most damaging pool left every round
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
+pub struct Greedy;
+
+// damage types a target takes less or more of
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Defenses {
+    // halved
+    pub resistant: Vec<DamageType>,
+    // doubled
+    pub vulnerable: Vec<DamageType>,
+    // none taken
+    pub immune: Vec<DamageType>,
+}
+
+// what happens to damage in excess of what it takes to drop an enemy
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Overkill {
+    Carry,
+    Waste,
+}
+
+// why dice notation or a config file didn't parse
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    // a term that isn't NdX or a whole number, or subtracted dice
+    Malformed(String),
+    // dice with fewer than 2 faces
+    UnknownDie(u32),
+    // a number too big to hold, or outside what a config field allows
+    OutOfRange(String),
+    // a config attack built from one that isn't defined
+    UnknownAttack(String),
+    // a config attack built, eventually, from itself
+    Cycle(String),
+    // TOML syntax or unknown fields, with the line when it's known
+    Config(String),
+    // a config file that couldn't be read
+    Io(String),
+}
+
+// probability of each damage total, in 64ths of a point so halved damage
+// and degrees stay exact
+type Pmf = BTreeMap<i64, f64>;
+
+// what the attacks so far have landed, for the riders and triggered attacks
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Landed {
+    // how many times the first attack to land rolled its dice
+    first: Option<i32>,
+    // the multiplier of the first attack to crit
+    first_crit: Option<i32>,
+    action: bool,
+    // distinct attacks that already added their first_hit_bonus
+    copies: Vec<usize>,
+}
+
+// chance of each (first, first_crit, action) of Landed, for the expected
+// rider damage
+type Chances = BTreeMap<(Option<i32>, Option<i32>, bool), f64>;
+
+// --- Methods ---
+
+impl Damage {
+    // count dice of the given faces, e.g. 2d6
+    pub fn dice(count: u32, faces: u32) -> Damage {
+        let mut dice = [0; DIE_FACES.len()];
+        if let Some(i) = DIE_FACES.iter().position(|&f| f == faces) {
+            dice[i] = count;
+        }
+
+        Damage {
+            dmg: count as f64 * die_ev(faces),
+            dice,
+            ..Default::default()
+        }
+    }
+
+    pub fn hit(&self) -> f64 {
+        self.dmg + self.fixed as f64
+    }
+
+    // critical hit doubles non-fixed damage
+    pub fn crit(&self) -> f64 {
+        self.crit_with(2)
+    }
+
+    // critical hit rolling the dice multiplier times
+    pub fn crit_with(&self, multiplier: i32) -> f64 {
+        self.fixed as f64 + multiplier as f64 * self.dmg
+    }
+
+    // crit_with scaled by the defenses, type by type
+    pub fn crit_vs(&self, multiplier: i32, defenses: &Defenses) -> f64 {
+        defenses
+            .kinds()
+            .iter()
+            .map(|&k| (defenses.multiplier(k) - 1.0) * self.of_type(k).crit_with(multiplier))
+            .sum::<f64>()
+            + self.crit_with(multiplier)
+    }
+
+    // all of the damage as the one type
+    pub fn of(self, kind: DamageType) -> Damage {
+        let mut typed = [(0.0, 0); DAMAGE_TYPES];
+        typed[kind as usize] = (self.dmg, self.fixed);
+
+        Damage { typed, ..self }
+    }
+
+    // Great Weapon Fighting, when all the dice are of the given faces
+    pub fn great_weapon_fighting(self, faces: i32) -> Damage {
+        let mut rerolled = self.rerolled;
+        if let Some(i) = DIE_FACES.iter().position(|&f| f == faces as u32) {
+            rerolled[i] = self.dice[i];
+        }
+
+        Damage {
+            rerolled,
+            ..self.scale_dice(great_weapon_fighting_value(faces) / die_ev(faces as u32))
+        }
+    }
+
+    // Savage Attacker, when the dice are count dice of the given faces
+    pub fn savage_attacker(self, count: i32, faces: i32) -> Damage {
+        Damage {
+            savage: Some((count as u32, faces as u32)),
+            ..self.scale_dice(
+                savage_attacker_value(count, faces) / (count as f64 * die_ev(faces as u32)),
+            )
+        }
+    }
+
+    // Great Weapon Fighting on every recorded die, whatever its faces. left
+    // as it is when some of dmg wasn't built from dice
+    fn great_weapon_fighting_all(self) -> Damage {
+        if self.lowest(1).is_none() || self.dmg == 0.0 {
+            return self;
+        }
+
+        let gain: f64 = DIE_FACES
+            .iter()
+            .zip(self.dice.iter().zip(self.rerolled))
+            .map(|(&f, (&n, r))| {
+                (n - r) as f64 * (great_weapon_fighting_value(f as i32) - die_ev(f))
+            })
+            .sum();
+
+        Damage {
+            rerolled: self.dice,
+            ..self.scale_dice((self.dmg + gain) / self.dmg)
+        }
+    }
+
+    // just the gain of Savage Attacker rerolling count dice of the faces
+    pub fn savage_gain(count: u32, faces: u32) -> Damage {
+        Damage {
+            dmg: savage_attacker_value(count as i32, faces as i32) - count as f64 * die_ev(faces),
+            savage: Some((count, faces)),
+            ..Default::default()
+        }
+    }
+
+    fn scale_dice(self, scale: f64) -> Damage {
+        let mut typed = self.typed;
+        for t in typed.iter_mut() {
+            t.0 *= scale;
+        }
+
+        Damage {
+            dmg: self.dmg * scale,
+            typed,
+            ..self
+        }
+    }
+
+    // the part of the damage of the type, untyped
+    pub fn of_type(&self, kind: DamageType) -> Damage {
+        let (dmg, fixed) = self.typed[kind as usize];
+
+        Damage {
+            dmg,
+            fixed,
+            ..Default::default()
+        }
+    }
+
+    // the dice rolled the given times, each die rolling 1. None when some of
+    // dmg wasn't built from dice
+    pub fn lowest(&self, rolls: i32) -> Option<f64> {
+        self.dice_total(rolls, |_| 1)
+    }
+
+    // the dice rolled the given times, each die rolling its faces
+    pub fn highest(&self, rolls: i32) -> Option<f64> {
+        self.dice_total(rolls, |faces| faces)
+    }
+
+    fn dice_total(&self, rolls: i32, face: impl Fn(u32) -> u32) -> Option<f64> {
+        let dice = self.dice.iter().zip(DIE_FACES);
+
+        // a reroll kept as the higher of two isn't told apart from a plain one
+        if let Some((_, faces)) = self.savage {
+            let i = DIE_FACES.iter().position(|&f| f == faces);
+            if i.is_some_and(|i| self.rerolled[i] > 0) {
+                return None;
+            }
+        }
+
+        let ev: f64 = dice
+            .clone()
+            .zip(self.rerolled)
+            .map(|((&n, f), r)| {
+                (n - r) as f64 * die_ev(f) + r as f64 * great_weapon_fighting_value(f as i32)
+            })
+            .sum::<f64>()
+            + self
+                .savage
+                .map_or(0.0, |(n, f)| Damage::savage_gain(n, f).dmg);
+        if (ev - self.dmg).abs() > 1e-9 {
+            return None;
+        }
+
+        // the gain is none when the reroll is all 1s, the most over 1s
+        let gain = self.savage.map_or(0, |(n, f)| n * (face(f) - 1));
+        let total: u32 = dice.map(|(&n, f)| n * face(f)).sum::<u32>() + gain;
+
+        Some(self.fixed as f64 + (rolls * total as i32) as f64)
+    }
+}
+
+impl Roll {
+    // chance the kept die shows one of the top faces of the d20
+    pub fn top(&self, faces: i32) -> f64 {
+        self.top_lucky(faces, false)
+    }
+
+    // top, with each die's natural 1 rerolled once when lucky
+    pub fn top_lucky(&self, faces: i32, lucky: bool) -> f64 {
+        let p = match lucky {
+            // each face but the 1 also shows up on the reroll
+            true if faces < 20 => faces as f64 * 21.0 / 400.0,
+            true => 1.0,
+            false => faces as f64 / 20.0,
+        };
+
+        match self {
+            Roll::Normal => p,
+            Roll::Advantage => 1.0 - (1.0 - p) * (1.0 - p),
+            Roll::Disadvantage => p * p,
+            Roll::ElvenAccuracy => 1.0 - (1.0 - p) * (1.0 - p) * (1.0 - p),
+        }
+    }
+
+    // chance the kept die shows one of a run of faces, with above faces over it
+    pub fn band(&self, faces: i32, above: i32) -> f64 {
+        self.band_lucky(faces, above, false)
+    }
+
+    pub fn band_lucky(&self, faces: i32, above: i32, lucky: bool) -> f64 {
+        match (self, lucky) {
+            (Roll::Normal, false) => faces as f64 / 20.0,
+            _ => self.top_lucky(faces + above, lucky) - self.top_lucky(above, lucky),
+        }
+    }
+}
+
+impl Attack {
+    // excludes crits, treats natural 1 as a miss
+    pub fn hit_chance(&self, ac: i32, roll: Roll) -> f64 {
+        self.over_hit_die(ac, |ac| {
+            roll.band_lucky(self.hit_faces(ac), self.crit_faces(), self.lucky)
+        })
+    }
+
+    // each roll of the hit die, as the amount it lowers AC by
+    fn hit_die_rolls(&self) -> Vec<i32> {
+        self.hit_die.map_or(vec![0], |n| (1..=n).collect())
+    }
+
+    // average of f over the hit die's rolls
+    fn over_hit_die(&self, ac: i32, f: impl Fn(i32) -> f64) -> f64 {
+        let rolls = self.hit_die_rolls();
+
+        rolls.iter().map(|k| f(ac - k)).sum::<f64>() / rolls.len() as f64
+    }
+
+    pub fn crit_chance(&self, roll: Roll) -> f64 {
+        roll.top_lucky(self.crit_faces(), self.lucky)
+    }
+
+    // faces out of 20 that hit without a crit
+    fn hit_faces(&self, ac: i32) -> i32 {
+        0.max(self.crit_range - 2.max(ac - self.hit))
+    }
+
+    fn crit_faces(&self) -> i32 {
+        21 - self.crit_range
+    }
+
+    // expected damage as a reduced (numerator, denominator), when every
+    // damage value is exact in 64ths (die averages are in halves)
+    pub fn expected_damage_rational(&self, ac: i32) -> Option<(i64, i64)> {
+        let on_hit = sixty_fourths(self.on_hit() + self.first_hit_bonus.hit())?;
+        let on_crit = sixty_fourths(
+            self.on_crit()
+                + self.crit_damage()
+                + self.first_hit_bonus.crit_with(self.crit_multiplier),
+        )?;
+
+        let nat20 = sixty_fourths(self.nat20_rider.unwrap_or_default().hit())?;
+
+        let rolls = self.hit_die_rolls();
+        let mut num = 0;
+
+        for k in &rolls {
+            let ac = ac - k;
+            let degrees = sixty_fourths(self.degrees.unwrap_or(0.0) * self.margins(ac) as f64)?;
+
+            num += self.hit_faces(ac) as i64 * on_hit
+                + self.crit_faces() as i64 * on_crit
+                + degrees
+                + nat20;
+        }
+
+        let den = 20 * 64 * rolls.len() as i64;
+        let div = gcd(num, den);
+
+        Some((num / div, den / div))
+    }
+
+    // expected damage lost to natural 1s missing even when the total would hit
+    pub fn fumble_cost(&self, ac: i32) -> f64 {
+        let unfumbled = self.over_hit_die(ac, |ac| {
+            0.max(self.crit_range - 1.max(ac - self.hit)) as f64 / 20.0
+        });
+
+        (unfumbled - self.hit_chance(ac, Roll::Normal))
+            * (self.on_hit() + self.first_hit_bonus.hit())
+    }
+
+    // damage of a regular hit, at least min_hit_damage. the floor applies
+    // to each roll of the dice when they're known, else to the average
+    pub fn on_hit(&self) -> f64 {
+        let hit = self.dmg.hit();
+
+        self.min_hit_damage
+            .map_or(hit, |min| match self.dmg.pmf(1) {
+                Some(pmf) => pmf
+                    .iter()
+                    .map(|(k, p)| p * (*k as f64 / 64.0).max(min))
+                    .sum(),
+                None => hit.max(min),
+            })
+    }
+
+    // the floored hit plus the dice rolled again
+    pub fn on_crit(&self) -> f64 {
+        let weapon = self.crit_weapon();
+
+        weapon.on_hit() + (self.crit_multiplier - 1) as f64 * weapon.dmg.dmg
+    }
+
+    // the attack as it's rolled on a crit, dmg_on_crit in place of dmg
+    fn crit_weapon(&self) -> Attack {
+        Attack {
+            dmg: self.dmg_on_crit.unwrap_or(self.dmg),
+            ..*self
+        }
+    }
+
+    // the crit only damage
+    fn crit_damage(&self) -> f64 {
+        self.crit.crit_with(self.crit_multiplier) + self.crit_extra.hit()
+    }
+
+    // expected damage excluding first_hit_bonus
+    fn base_damage(&self, ac: i32, roll: Roll) -> f64 {
+        self.hit_chance(ac, roll) * self.on_hit()
+            + self.crit_chance(roll) * (self.on_crit() + self.crit_damage())
+            + self.degrees_damage(ac, roll)
+            + self.nat20_damage(roll)
+    }
+
+    // the rider fires only on a natural 20 and isn't doubled as crit damage
+    fn nat20_damage(&self, roll: Roll) -> f64 {
+        self.nat20_rider
+            .map_or(0.0, |d| roll.top_lucky(1, self.lucky) * d.hit())
+    }
+
+    // sum of how far each face, natural 1 excluded, beats AC
+    fn margins(&self, ac: i32) -> i32 {
+        (2..=20).map(|face| 0.max(face + self.hit - ac)).sum()
+    }
+
+    fn degrees_damage(&self, ac: i32, roll: Roll) -> f64 {
+        self.degrees.map_or(0.0, |d| {
+            self.over_hit_die(ac, |ac| match (roll, self.lucky) {
+                (Roll::Normal, false) => d * self.margins(ac) as f64 / 20.0,
+                // weight each face by the chance it's the one kept
+                _ => (2..=20)
+                    .map(|face| {
+                        d * roll.band_lucky(1, 20 - face, self.lucky)
+                            * 0.max(face + self.hit - ac) as f64
+                    })
+                    .sum(),
+            })
+        })
+    }
+
+    // expected first_hit_bonus damage when this attack lands
+    fn first_hit_damage(&self, ac: i32, roll: Roll) -> f64 {
+        self.hit_chance(ac, roll) * self.first_hit_bonus.hit()
+            + self.crit_chance(roll) * self.first_hit_bonus.crit_with(self.crit_multiplier)
+    }
+
+    // expected damage gained per point of hit and per point of fixed damage
+    pub fn sensitivity(&self, ac: i32) -> (f64, f64) {
+        // the next point of hit turns a miss into a hit, short of the crit range
+        let new_hit = self.over_hit_die(ac, |ac| {
+            if (3..=self.crit_range).contains(&(ac - self.hit)) {
+                (self.on_hit() + self.first_hit_bonus.hit()) / 20.0
+            } else {
+                0.0
+            }
+        });
+
+        // and adds a degree on every face that already meets AC
+        let new_degrees = self.degrees.map_or(0.0, |d| {
+            self.over_hit_die(ac, |ac| {
+                d * (2..=20).filter(|face| face + self.hit >= ac).count() as f64 / 20.0
+            })
+        });
+
+        // fixed damage is lost under the min_hit_damage floor
+        let floored = self.min_hit_damage.is_some_and(|min| self.dmg.hit() < min);
+        let per_dmg = if floored {
+            0.0
+        } else {
+            self.hit_chance(ac, Roll::Normal) + self.crit_chance(Roll::Normal)
+        };
+
+        (new_hit + new_degrees, per_dmg)
+    }
+
+    // trade accuracy for fixed damage, as the -5/+10 feats do
+    fn power_attack(&self, hit_penalty: i32, dmg_bonus: i32) -> Attack {
+        *self
+            + Attack {
+                hit: -hit_penalty,
+                dmg: Damage {
+                    fixed: dmg_bonus,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }
+    }
+
+    // the weapon's dice rolled as dice of the faces on a crit, rather than
+    // doubled, e.g. a d8 weapon rolling 2d12. unchanged unless dmg is all
+    // plain recorded dice
+    pub fn crit_die_override(&self, faces: u32) -> Attack {
+        let plain = self.dmg.savage.is_none() && self.dmg.rerolled == [0; DIE_FACES.len()];
+        if !plain || self.dmg.lowest(1).is_none() || self.dmg.dmg == 0.0 {
+            return *self;
+        }
+
+        let dice = Damage::dice(self.dmg.dice.iter().sum(), faces);
+
+        Attack {
+            dmg_on_crit: Some(Damage {
+                dice: dice.dice,
+                ..self.dmg.scale_dice(dice.dmg / self.dmg.dmg)
+            }),
+            ..*self
+        }
+    }
+
+    // Brutal Critical, count extra weapon dice of the faces on a crit
+    pub fn brutal_critical(&self, count: u32, faces: u32) -> Attack {
+        *self
+            + Attack {
+                crit_extra: Damage::dice(count, faces),
+                ..Default::default()
+            }
+    }
+
+    // the Dueling style, +2 damage with a lone one-handed weapon
+    pub fn dueling(&self) -> Attack {
+        *self
+            + Attack {
+                dmg: Damage {
+                    fixed: 2,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }
+    }
+
+    pub fn sharpshooter(&self) -> Attack {
+        self.power_attack(5, 10)
+    }
+
+    pub fn great_weapon_master(&self) -> Attack {
+        self.power_attack(5, 10)
+    }
+
+    // the AC, going up the range, past which the -5/+10 of Sharpshooter or
+    // Great Weapon Master stops being at least as good as the plain attack.
+    // at ACs only crits can hit it wins again
+    pub fn power_attack_threshold(&self, range: RangeInclusive<i32>) -> Option<i32> {
+        threshold(self, &self.sharpshooter(), range)
+    }
+
+    // least damage when it lands, every die rolling 1. when only a crit can
+    // hit the AC that's a crit
+    pub fn min_damage(&self, ac: i32) -> Option<f64> {
+        let rolls = self.least_landing_rolls(ac);
+
+        Some(self.min_landing(ac)? + self.first_hit_bonus.lowest(rolls)?)
+    }
+
+    // most damage, a natural 20 with every die rolling its faces
+    pub fn max_damage(&self, ac: i32) -> Option<f64> {
+        Some(self.max_landing(ac)? + self.first_hit_bonus.highest(self.crit_multiplier)?)
+    }
+
+    // how often the dice are rolled by the weakest hit
+    fn least_landing_rolls(&self, ac: i32) -> i32 {
+        if self.hit_chance(ac, Roll::Normal) > 0.0 {
+            1
+        } else {
+            self.crit_multiplier
+        }
+    }
+
+    // min and max excluding first_hit_bonus
+    fn min_landing(&self, ac: i32) -> Option<f64> {
+        let low = match self.least_landing_rolls(ac) {
+            1 => self.dmg.lowest(1)?,
+            m => {
+                self.crit_weapon().dmg.lowest(m)?
+                    + self.crit.lowest(m)?
+                    + self.crit_extra.lowest(1)?
+            }
+        };
+
+        Some(self.min_hit_damage.map_or(low, |min| low.max(min)))
+    }
+
+    fn max_landing(&self, ac: i32) -> Option<f64> {
+        let m = self.crit_multiplier;
+        let margin = 0.max(20 + self.hit + self.hit_die.unwrap_or(0) - ac);
+        let weapon = self.crit_weapon().dmg;
+        let hit = weapon.highest(1)?;
+
+        Some(
+            self.min_hit_damage.map_or(hit, |min| hit.max(min)) + weapon.highest(m)?
+                - weapon.highest(1)?
+                + self.crit.highest(m)?
+                + self.crit_extra.highest(1)?
+                + self.degrees.unwrap_or(0.0) * margin as f64
+                + self.nat20_rider.map_or(Some(0.0), |d| d.highest(1))?,
+        )
+    }
+
+    pub fn builder() -> AttackBuilder {
+        AttackBuilder::default()
+    }
+}
+
+impl AttackBuilder {
+    pub fn hit(mut self, hit: i32) -> AttackBuilder {
+        self.atk.hit += hit;
+        self
+    }
+
+    pub fn dice(mut self, count: u32, faces: u32) -> AttackBuilder {
+        self.atk.dmg = self.atk.dmg + Damage::dice(count, faces);
+        self
+    }
+
+    pub fn fixed(mut self, fixed: i32) -> AttackBuilder {
+        self.atk.dmg.fixed += fixed;
+        self
+    }
+
+    // extra dice doubled on a crit, e.g. Deft Strike
+    pub fn crit_dice(mut self, count: u32, faces: u32) -> AttackBuilder {
+        self.atk.crit = self.atk.crit + Damage::dice(count, faces);
+        self
+    }
+
+    pub fn crit_range(mut self, crit_range: i32) -> AttackBuilder {
+        self.atk.crit_range = crit_range;
+        self
+    }
+
+    pub fn build(self) -> Attack {
+        self.atk
+    }
+}
+
+impl SaveAttack {
+    // the target fails when its save falls short of the DC
+    pub fn fail_chance(&self) -> f64 {
+        (self.dc - 1 - self.save_bonus_of_target).clamp(0, 20) as f64 / 20.0
+    }
+}
+
+impl Turn {
+    // Favored Foe
+    pub fn foe(&self) -> Turn {
+        Turn {
+            once_on_hit: self.once_on_hit + Damage::dice(1, 4),
+            ..self.clone()
+        }
+    }
+
+    // Colossus Slayer, against a target below its hp max. like Favored Foe
+    // it lands once, on the first hit, and the two add up
+    pub fn colossus_slayer(&self) -> Turn {
+        Turn {
+            once_on_hit: self.once_on_hit + Damage::dice(1, 8),
+            ..self.clone()
+        }
+    }
+
+    // Bless, a d4 added to every attack roll
+    pub fn bless(&self) -> Turn {
+        self.clone()
+            + Attack {
+                hit_die: Some(4),
+                ..Default::default()
+            }
+    }
+
+    // Hexblade's Curse, the proficiency bonus on every hit against the
+    // cursed target and crits on at least 19-20. attacks at other creatures
+    // are unaffected
+    pub fn hexblades_curse(&self, prof: i32) -> Turn {
+        let cursed = self.clone()
+            + Attack {
+                dmg: Damage {
+                    fixed: prof,
+                    ..Default::default()
+                },
+                crit_range: 19,
+                ..Default::default()
+            };
+
+        Turn {
+            other_targets: self.other_targets.clone(),
+            cleave: self.cleave,
+            ..cursed
+        }
+    }
+
+    pub fn rider(self, rider: OncePerTurnRider) -> Turn {
+        let mut turn = self;
+        turn.riders.push(rider);
+        turn
+    }
+
+    // once_on_hit and the riders that land, with how many times their dice
+    // are rolled, given the rolls for the first hit and for the first crit.
+    // the best of each exclusive group on average against the defenses
+    fn landed_riders(
+        &self,
+        first: Option<i32>,
+        first_crit: Option<i32>,
+        defenses: &Defenses,
+    ) -> Vec<(&Damage, i32)> {
+        let mut landed = vec![];
+        if let Some(rolls) = first {
+            landed.push((&self.once_on_hit, rolls));
+        }
+
+        let mut best: Vec<(&String, &Damage, i32)> = vec![];
+        for r in &self.riders {
+            let rolls = match (r.crit_only, first, first_crit) {
+                (true, _, Some(m)) | (false, Some(m), _) => m,
+                _ => continue,
+            };
+
+            match &r.stacking {
+                Stacking::Stackable => landed.push((&r.dmg, rolls)),
+                Stacking::Exclusive(group) => match best.iter_mut().find(|(g, _, _)| *g == group) {
+                    Some(b) => {
+                        if r.dmg.crit_vs(rolls, defenses) > b.1.crit_vs(b.2, defenses) {
+                            *b = (group, &r.dmg, rolls);
+                        }
+                    }
+                    None => best.push((group, &r.dmg, rolls)),
+                },
+            }
+        }
+
+        landed.extend(best.into_iter().map(|(_, d, rolls)| (d, rolls)));
+        landed
+    }
+
+    // expected damage of once_on_hit and the riders against the defenses
+    fn rider_damage(
+        &self,
+        ac_of: impl Fn(usize) -> i32,
+        roll_at: impl Fn(i32) -> Roll,
+        defenses: &Defenses,
+    ) -> f64 {
+        self.landed_chances(ac_of, roll_at)
+            .iter()
+            .map(|(&(first, first_crit, _), p)| {
+                p * self
+                    .landed_riders(first, first_crit, defenses)
+                    .iter()
+                    .map(|(d, rolls)| d.crit_vs(*rolls, defenses))
+                    .sum::<f64>()
+            })
+            .sum()
+    }
+
+    // chance of what the action, bonus action and triggered attacks land
+    fn landed_chances(
+        &self,
+        ac_of: impl Fn(usize) -> i32,
+        roll_at: impl Fn(i32) -> Roll,
+    ) -> Chances {
+        let mut states = Chances::from([((None, None, false), 1.0)]);
+
+        for (n, d) in self.action.iter().chain(&self.bonus_action).enumerate() {
+            let ac = ac_of(n);
+            states = land(states, d, ac, roll_at(ac), n < self.action.len(), true);
+        }
+
+        // only made when the actions missed, so it may be the first hit
+        for (trigger, d) in &self.triggered {
+            if *trigger == Trigger::ActionsMiss {
+                let (missed, rest) = states.into_iter().partition(|(s, _)| !s.2);
+                states = land(missed, d, ac_of(0), roll_at(ac_of(0)), false, false);
+                states.extend(rest);
+            }
+        }
+
+        states
+    }
+
+    // expected damage of each attack in order, then the once per turn riders
+    pub fn stacked_contributions(&self, ac: i32) -> Vec<(String, f64)> {
+        self.contributions(|_| ac, |_| Roll::Normal)
+    }
+
+    // expected damage with the nth action or bonus action attack against
+    // the nth AC. the riders, triggered attacks and reactions go with the
+    // first target. None unless there's one AC per action and bonus action
+    // attack
+    pub fn expected_damage_multi_ac(&self, acs: &[i32]) -> Option<f64> {
+        if acs.len() != self.action.len() + self.bonus_action.len() {
+            return None;
+        }
+
+        Some(
+            self.contributions(|i| acs[i], |_| Roll::Normal)
+                .iter()
+                .map(|(_, d)| d)
+                .sum(),
+        )
+    }
+
+    // expected_damage_multi_ac with advantage on the attacks at the ACs the
+    // predicate picks, e.g. those at a prone target
+    pub fn expected_damage_multi_ac_with(
+        &self,
+        acs: &[i32],
+        advantage: impl Fn(i32) -> bool,
+    ) -> Option<f64> {
+        if acs.len() != self.action.len() + self.bonus_action.len() {
+            return None;
+        }
+
+        let roll_at = |ac| match advantage(ac) {
+            true => Roll::Advantage,
+            false => Roll::Normal,
+        };
+
+        Some(
+            self.contributions(|i| acs[i], roll_at)
+                .iter()
+                .map(|(_, d)| d)
+                .sum(),
+        )
+    }
+
+    // expected damage with the action and bonus action attacks dealt out
+    // round-robin across the targets. the riders land once, on whichever
+    // target is hit first, and triggered attacks go with the first target.
+    // None when there are no targets
+    pub fn expected_damage_spread(&self, acs: &[i32]) -> Option<f64> {
+        if acs.is_empty() {
+            return None;
+        }
+
+        Some(
+            self.contributions(|i| acs[i % acs.len()], |_| Roll::Normal)
+                .iter()
+                .map(|(_, d)| d)
+                .sum(),
+        )
+    }
+
+    // stacked_contributions with the AC of each action and bonus action attack
+    // and the roll made at each AC
+    fn contributions(
+        &self,
+        ac_of: impl Fn(usize) -> i32,
+        roll_at: impl Fn(i32) -> Roll,
+    ) -> Vec<(String, f64)> {
+        let mut contributions = vec![];
+        let mut miss = 1.0;
+        let mut no_crit = 1.0;
+        let mut action_miss = 1.0;
+        // chance no copy of the first action attack has landed, for cleave
+        let mut weapon_miss = 1.0;
+
+        // chance no copy of an attack has landed yet, for its first_hit_bonus
+        let mut copies: Vec<(&Attack, f64)> = vec![];
+
+        let labeled = self
+            .action
+            .iter()
+            .enumerate()
+            .map(|(i, a)| (format!("action {}", i + 1), a))
+            .chain(
+                self.bonus_action
+                    .iter()
+                    .enumerate()
+                    .map(|(i, a)| (format!("bonus action {}", i + 1), a)),
+            );
+
+        for (n, (label, d)) in labeled.enumerate() {
+            let ac = ac_of(n);
+            let roll = roll_at(ac);
+            let mut dmg = d.base_damage(ac, roll);
+            let crit_chance = d.crit_chance(roll);
+            let landing = d.hit_chance(ac, roll) + crit_chance;
+            miss *= 1.0 - landing;
+            no_crit *= 1.0 - crit_chance;
+
+            if n < self.action.len() {
+                action_miss *= 1.0 - landing;
+            }
+            if self.action.first() == Some(d) {
+                weapon_miss *= 1.0 - landing;
+            }
+
+            if d.first_hit_bonus != Damage::default() {
+                let i = match copies.iter().position(|(a, _)| *a == d) {
+                    Some(i) => i,
+                    None => {
+                        copies.push((d, 1.0));
+                        copies.len() - 1
+                    }
+                };
+
+                dmg += copies[i].1 * d.first_hit_damage(ac, roll);
+                copies[i].1 *= 1.0 - landing;
+            }
+
+            contributions.push((label, dmg));
+        }
+
+        let landed = 1.0 - miss;
+
+        for (i, (trigger, d)) in self.triggered.iter().enumerate() {
+            let ac = ac_of(0);
+
+            let (label, chance) = match trigger {
+                // a triggering hit or crit already landed the once_on_hit rider
+                Trigger::Crit => ("on crit", 1.0 - no_crit),
+                Trigger::Hit => ("on hit", landed),
+                Trigger::ActionsMiss => ("on miss", action_miss),
+            };
+
+            contributions.push((
+                format!("{} {}", label, i + 1),
+                chance * d.expected_damage_with(ac, roll_at(ac)),
+            ));
+        }
+
+        let chance = self.reaction_chance.unwrap_or(1.0);
+        for (i, &d) in self.reaction.iter().enumerate() {
+            contributions.push((
+                format!("reaction {}", i + 1),
+                chance
+                    * self
+                        .reaction_turn(d)
+                        .expected_damage_with(ac_of(0), roll_at(ac_of(0))),
+            ));
+        }
+
+        for (i, &(ac, d)) in self.other_targets.iter().enumerate() {
+            contributions.push((
+                format!("other target {}", i + 1),
+                alone(d).expected_damage_with(ac, roll_at(ac)),
+            ));
+        }
+
+        if let Some((ac, d)) = self.cleave {
+            contributions.push((
+                "cleave".to_string(),
+                (1.0 - weapon_miss) * alone(d).expected_damage_with(ac, roll_at(ac)),
+            ));
+        }
+
+        for (i, save) in self.saves.iter().enumerate() {
+            contributions.push((
+                format!("save {}", i + 1),
+                save.expected_damage_with(ac_of(0), roll_at(ac_of(0))),
+            ));
+        }
+
+        contributions.push((
+            "rider".to_string(),
+            self.rider_damage(&ac_of, &roll_at, &Defenses::default()),
+        ));
+
+        contributions
+    }
+
+    // sensitivity summed over action and bonus action attacks, ignoring the
+    // riders and triggered attacks
+    pub fn sensitivity(&self, ac: i32) -> (f64, f64) {
+        self.action
+            .iter()
+            .chain(self.bonus_action.iter())
+            .map(|a| a.sensitivity(ac))
+            .fold((0.0, 0.0), |(h, d), (dh, dd)| (h + dh, d + dd))
+    }
+
+    // fumble cost summed over action and bonus action attacks, ignoring the
+    // small change to the riders' first hit
+    pub fn fumble_cost(&self, ac: i32) -> f64 {
+        self.action
+            .iter()
+            .chain(self.bonus_action.iter())
+            .map(|a| a.fumble_cost(ac))
+            .sum()
+    }
+
+    // least damage when every action, bonus action and other target attack
+    // lands and every save fails, with every die rolling 1. it leaves out the crit only
+    // riders, the attacks triggered by a miss or a crit, and the reactions.
+    // None when some damage wasn't built from dice
+    pub fn min_damage(&self, ac: i32) -> Option<f64> {
+        let attacks: Vec<&Attack> = self.action.iter().chain(&self.bonus_action).collect();
+
+        let mut total = 0.0;
+        for a in &attacks {
+            total += a.min_landing(ac)?;
+        }
+        for a in self.first_hits() {
+            total += a.first_hit_bonus.lowest(a.least_landing_rolls(ac))?;
+        }
+
+        if let Some(first) = attacks.first() {
+            let rolls = first.least_landing_rolls(ac);
+            total += self.once_on_hit.lowest(rolls)?;
+            total += self.riders_total(false, |d| d.lowest(rolls))?;
+
+            for (trigger, a) in &self.triggered {
+                if *trigger == Trigger::Hit {
+                    total += a.min_damage(ac)?;
+                }
+            }
+        }
+        for &(ac, a) in &self.other_targets {
+            total += alone(a).min_damage(ac)?;
+        }
+        if let (Some((ac, a)), false) = (self.cleave, self.action.is_empty()) {
+            total += alone(a).min_damage(ac)?;
+        }
+
+        Some(total + self.saves_total(|d| d.lowest(1))?)
+    }
+
+    // most damage when every action, bonus action, reaction and other target
+    // attack is a natural 20 with every die rolling its faces, and every save fails
+    pub fn max_damage(&self, ac: i32) -> Option<f64> {
+        let attacks: Vec<&Attack> = self.action.iter().chain(&self.bonus_action).collect();
+
+        let mut total = 0.0;
+        for a in &attacks {
+            total += a.max_landing(ac)?;
+        }
+        for a in self.first_hits() {
+            total += a.first_hit_bonus.highest(a.crit_multiplier)?;
+        }
+
+        if let Some(first) = attacks.first() {
+            let rolls = first.crit_multiplier;
+            total += self.once_on_hit.highest(rolls)?;
+            total += self.riders_total(true, |d| d.highest(rolls))?;
+
+            for (trigger, a) in &self.triggered {
+                if *trigger != Trigger::ActionsMiss {
+                    total += a.max_damage(ac)?;
+                }
+            }
+        }
+        for &a in &self.reaction {
+            total += self.reaction_turn(a).max_damage(ac)?;
+        }
+        for &(ac, a) in &self.other_targets {
+            total += alone(a).max_damage(ac)?;
+        }
+        if let (Some((ac, a)), false) = (self.cleave, self.action.is_empty()) {
+            total += alone(a).max_damage(ac)?;
+        }
+
+        Some(total + self.saves_total(|d| d.highest(1))?)
+    }
+
+    // highest AC of the range where max_damage still drops an enemy with the
+    // hp, so a one turn kill is possible. None when it never is, or some
+    // damage wasn't built from dice
+    pub fn max_ac_for_possible_kill(&self, hp: i32, range: RangeInclusive<i32>) -> Option<i32> {
+        range
+            .rev()
+            .find(|&ac| self.max_damage(ac).is_some_and(|max| max >= hp as f64))
+    }
+
+    // a reaction as the turn it's made on, with the once per turn riders
+    fn reaction_turn(&self, atk: Attack) -> Turn {
+        Turn {
+            action: vec![atk],
+            once_on_hit: self.once_on_hit,
+            riders: self.riders.clone(),
+            ..Default::default()
+        }
+    }
+
+    // one of each distinct attack, for its first_hit_bonus
+    fn first_hits(&self) -> Vec<&Attack> {
+        let mut distinct: Vec<&Attack> = vec![];
+        for a in self.action.iter().chain(&self.bonus_action) {
+            if !distinct.contains(&a) {
+                distinct.push(a);
+            }
+        }
+
+        distinct
+    }
+
+    // riders all stacking, the best of each exclusive group
+    fn riders_total(&self, crit: bool, value: impl Fn(&Damage) -> Option<f64>) -> Option<f64> {
+        let mut total = 0.0;
+        let mut best: Vec<(&String, f64)> = vec![];
+
+        for r in self.riders.iter().filter(|r| crit || !r.crit_only) {
+            let v = value(&r.dmg)?;
+
+            match &r.stacking {
+                Stacking::Stackable => total += v,
+                Stacking::Exclusive(group) => match best.iter_mut().find(|(g, _)| *g == group) {
+                    Some((_, b)) => *b = b.max(v),
+                    None => best.push((group, v)),
+                },
+            }
+        }
+
+        Some(total + best.iter().map(|(_, v)| v).sum::<f64>())
+    }
+
+    // every save failed, halved by evasion
+    fn saves_total(&self, value: impl Fn(&Damage) -> Option<f64>) -> Option<f64> {
+        let mut total = 0.0;
+        for save in &self.saves {
+            let taken = if save.half_on_save && save.evasion {
+                0.5
+            } else {
+                1.0
+            };
+            total += taken * value(&save.dmg)?;
+        }
+
+        Some(total)
+    }
+
+    // chance each action and bonus action attack lands, crits included
+    fn landing_chances(&self, ac: i32) -> impl Iterator<Item = f64> + '_ {
+        self.action
+            .iter()
+            .chain(self.bonus_action.iter())
+            .map(move |a| a.hit_chance(ac, Roll::Normal) + a.crit_chance(Roll::Normal))
+    }
+
+    // expected number of action and bonus action attacks that land
+    pub fn expected_hits(&self, ac: i32) -> f64 {
+        self.landing_chances(ac).sum()
+    }
+
+    // chance that exactly n of the action and bonus action attacks land, for
+    // each n from 0 up to the number of attacks
+    pub fn hit_count_distribution(&self, ac: i32) -> Vec<f64> {
+        let mut pmf = vec![1.0];
+
+        for p in self.landing_chances(ac) {
+            let mut next = vec![0.0; pmf.len() + 1];
+
+            for (n, q) in pmf.iter().enumerate() {
+                next[n] += q * (1.0 - p);
+                next[n + 1] += q * p;
+            }
+
+            pmf = next;
+        }
+
+        pmf
+    }
+
+    // every action and bonus action attack with the -5/+10 of Sharpshooter
+    // or Great Weapon Master
+    pub fn sharpshooter(&self) -> Turn {
+        Turn {
+            action: self.action.iter().map(|a| a.sharpshooter()).collect(),
+            bonus_action: self.bonus_action.iter().map(|a| a.sharpshooter()).collect(),
+            ..self.clone()
+        }
+    }
+
+    // power_attack_threshold for the turn, so the riders count too
+    pub fn power_attack_threshold(&self, range: RangeInclusive<i32>) -> Option<i32> {
+        threshold(self, &self.sharpshooter(), range)
+    }
+
+    // expected damage taking sharpshooter on exactly the attacks it helps
+    pub fn expected_damage_optimal_power_attack(&self, ac: i32) -> f64 {
+        let best_at = |a: &Attack, ac: i32| {
+            let sharp = a.sharpshooter();
+
+            if sharp.expected_damage(ac) > a.expected_damage(ac) {
+                sharp
+            } else {
+                *a
+            }
+        };
+        let best = |a: &Attack| best_at(a, ac);
+
+        Turn {
+            action: self.action.iter().map(best).collect(),
+            bonus_action: self.bonus_action.iter().map(best).collect(),
+            reaction: self.reaction.iter().map(best).collect(),
+            other_targets: self
+                .other_targets
+                .iter()
+                .map(|&(ac, a)| (ac, best_at(&a, ac)))
+                .collect(),
+            cleave: self.cleave.map(|(ac, a)| (ac, best_at(&a, ac))),
+            triggered: self.triggered.iter().map(|(t, a)| (*t, best(a))).collect(),
+            ..self.clone()
+        }
+        .expected_damage(ac)
+    }
+
+    // the attack granted by a weapon mastery
+    pub fn mastery(self, mastery: WeaponMastery, atk: Attack) -> Turn {
+        let mut turn = self;
+
+        match mastery {
+            WeaponMastery::Nick => turn.action.push(atk),
+            WeaponMastery::Cleave(ac) => turn.cleave = Some((ac, atk)),
+        }
+
+        turn
+    }
+
+    // Horde Breaker, one more attack with the first action weapon at a
+    // second creature with the AC
+    pub fn horde_breaker(self, ac: i32) -> Turn {
+        let mut turn = self;
+
+        if let Some(&atk) = turn.action.first() {
+            turn.other_targets.push((ac, atk));
+        }
+
+        turn
+    }
+
+    // Polearm Master, a bonus action attack with the butt end for a d4
+    pub fn polearm_master(self, hit: i32) -> Turn {
+        let mut turn = self;
+        turn.bonus_action
+            .push(Attack::builder().hit(hit).dice(1, 4).build());
+        turn
+    }
+
+    // Crossbow Expert, a bonus action hand crossbow shot. Sharpshooter is
+    // taken shot by shot, so pass shot.sharpshooter() to penalize it too
+    pub fn crossbow_expert(self, shot: Attack) -> Turn {
+        let mut turn = self;
+        turn.bonus_action.push(shot);
+        turn
+    }
+
+    // a bonus action attack made only if every action attack misses
+    pub fn bonus_on_all_miss(self, atk: Attack) -> Turn {
+        let mut turn = self;
+        turn.triggered.push((Trigger::ActionsMiss, atk));
+        turn
+    }
+
+    // the Two-Weapon Fighting style, the ability modifier added to the
+    // damage of the off-hand attacks
+    pub fn two_weapon_fighting(self, mod_damage: i32) -> Turn {
+        let dmg = Damage {
+            fixed: mod_damage,
+            ..Default::default()
+        };
+
+        Turn {
+            bonus_action: self.bonus_action.into_iter().map(|a| a + dmg).collect(),
+            ..self
+        }
+    }
+
+    // count d6 of Sneak Attack on the first hit, doubled when it's a crit.
+    // adds to what else lands on the first hit, e.g. Favored Foe
+    pub fn sneak_attack(self, count: u32) -> Turn {
+        Turn {
+            once_on_hit: self.once_on_hit + Damage::dice(count, 6),
+            ..self
+        }
+    }
+
+    // count d8 of Divine Smite on the first hit, doubled when it's a crit
+    pub fn divine_smite(self, count: u32) -> Turn {
+        self.rider(OncePerTurnRider {
+            name: "smite".to_string(),
+            dmg: Damage::dice(count, 8).of(DamageType::Radiant),
+            ..Default::default()
+        })
+    }
+
+    // Divine Smite saved for the first hit of the attack doing the most
+    // expected damage against ac, on whichever copy of it lands first
+    pub fn divine_smite_best(self, count: u32, ac: i32) -> Turn {
+        let mut turn = self;
+
+        let best = turn
+            .action
+            .iter()
+            .chain(&turn.bonus_action)
+            .copied()
+            .reduce(|a, b| {
+                if b.expected_damage(ac) > a.expected_damage(ac) {
+                    b
+                } else {
+                    a
+                }
+            });
+
+        if let Some(best) = best {
+            let smite = Damage::dice(count, 8).of(DamageType::Radiant);
+
+            for atk in turn.action.iter_mut().chain(turn.bonus_action.iter_mut()) {
+                if *atk == best {
+                    atk.first_hit_bonus = atk.first_hit_bonus + smite;
+                }
+            }
+        }
+
+        turn
+    }
+
+    // count d8 of Divine Smite held for the first crit of the turn
+    pub fn smite_on_crit(self, count: u32) -> Turn {
+        self.rider(OncePerTurnRider {
+            name: "smite".to_string(),
+            dmg: Damage::dice(count, 8),
+            crit_only: true,
+            ..Default::default()
+        })
+    }
+
+    // Savage Attacker on the first hit, rerolling count weapon dice of the
+    // faces. a crit doubles the gain rather than rerolling all its dice
+    pub fn savage_attacker(self, count: i32, faces: i32) -> Turn {
+        self.rider(OncePerTurnRider {
+            name: "Savage Attacker".to_string(),
+            dmg: Damage::savage_gain(count as u32, faces as u32),
+            ..Default::default()
+        })
+    }
+
+    // the turn with its damage dice rolled under the policy
+    pub fn with_policy(&self, policy: DicePolicy) -> Turn {
+        match policy {
+            DicePolicy::Standard => self.clone(),
+            DicePolicy::GreatWeaponFighting => self.map_damage(Damage::great_weapon_fighting_all),
+            DicePolicy::SavageAttacker => {
+                let weapon = self
+                    .action
+                    .first()
+                    .map_or([0; DIE_FACES.len()], |a| a.dmg.dice);
+
+                match weapon.iter().zip(DIE_FACES).rev().find(|(&n, _)| n > 0) {
+                    Some((&count, faces)) => {
+                        self.clone().savage_attacker(count as i32, faces as i32)
+                    }
+                    None => self.clone(),
+                }
+            }
+        }
+    }
+
+    pub fn expected_damage_with_policy(&self, ac: i32, policy: DicePolicy) -> f64 {
+        self.with_policy(policy).expected_damage(ac)
+    }
+
+    // every Damage of the turn passed through f
+    fn map_damage(&self, f: impl Fn(Damage) -> Damage) -> Turn {
+        let atk = |a: &Attack| Attack {
+            dmg: f(a.dmg),
+            crit: f(a.crit),
+            crit_extra: f(a.crit_extra),
+            dmg_on_crit: a.dmg_on_crit.map(&f),
+            first_hit_bonus: f(a.first_hit_bonus),
+            nat20_rider: a.nat20_rider.map(&f),
+            ..*a
+        };
+
+        Turn {
+            action: self.action.iter().map(atk).collect(),
+            bonus_action: self.bonus_action.iter().map(atk).collect(),
+            reaction: self.reaction.iter().map(atk).collect(),
+            other_targets: self
+                .other_targets
+                .iter()
+                .map(|(ac, a)| (*ac, atk(a)))
+                .collect(),
+            cleave: self.cleave.map(|(ac, a)| (ac, atk(&a))),
+            once_on_hit: f(self.once_on_hit),
+            riders: self
+                .riders
+                .iter()
+                .map(|r| OncePerTurnRider {
+                    dmg: f(r.dmg),
+                    ..r.clone()
+                })
+                .collect(),
+            triggered: self.triggered.iter().map(|(t, a)| (*t, atk(a))).collect(),
+            saves: self
+                .saves
+                .iter()
+                .map(|s| SaveAttack {
+                    dmg: f(s.dmg),
+                    ..*s
+                })
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    // an additional attack when the turn scores its first crit
+    pub fn extra_attack_on_crit(self, atk: Attack) -> Turn {
+        let mut turn = self;
+        turn.triggered.push((Trigger::Crit, atk));
+        turn
+    }
+
+    // Halfling Luck or the Lucky feat on every attack, rerolling natural 1s
+    pub fn lucky(self) -> Turn {
+        self + Attack {
+            lucky: true,
+            ..Default::default()
+        }
+    }
+
+    // +2 to the attacking stat, at an even total: +1 to hit and damage on every
+    // attack, riders are unaffected
+    pub fn asi(self) -> Turn {
+        self + Attack {
+            hit: 1,
+            dmg: Damage {
+                fixed: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    // Action Surge, a second action's attacks for one turn. later turns go
+    // back to this one, e.g. as a Pool with a single use
+    pub fn action_surge(&self) -> Turn {
+        let mut turn = self.clone();
+        turn.action.extend_from_slice(&self.action);
+        turn
+    }
+
+    // Hunter's Mark
+    pub fn mark(&self) -> HuntersMark {
+        let bonus = Damage::dice(1, 6);
+
+        let mark = self.clone() + bonus;
+
+        HuntersMark {
+            unmodified: self.clone(),
+            // casting it takes the bonus action
+            first_turn: Turn {
+                bonus_action: vec![],
+                ..mark.clone()
+            },
+            max_damage: mark,
+        }
+    }
+
+    // rounds of focus-fire to drop count enemies with the given hp
+    pub fn rounds_to_clear(&self, ac: i32, enemy_hp: i32, count: u32, overkill: Overkill) -> f64 {
+        let dpr = self.expected_damage(ac);
+
+        if dpr <= 0.0 {
+            return f64::INFINITY;
+        }
+
+        match overkill {
+            Overkill::Carry => (enemy_hp * count as i32) as f64 / dpr,
+            // the rest of the killing round is lost, so each enemy takes whole rounds
+            Overkill::Waste => count as f64 * (enemy_hp as f64 / dpr).ceil(),
+        }
+    }
+
+    // area under the damage vs AC curve (trapezoidal rule), normalized by
+    // range width so it reads as an average damage per round
+    pub fn auc(&self, range: RangeInclusive<i32>) -> f64 {
+        let (lo, hi) = (*range.start(), *range.end());
+
+        if lo >= hi {
+            return self.expected_damage(lo);
+        }
+
+        let area: f64 = (lo..hi)
+            .map(|ac| (self.expected_damage(ac) + self.expected_damage(ac + 1)) / 2.0)
+            .sum();
+
+        area / (hi - lo) as f64
+    }
+}
+
+impl Defenses {
+    // each type with a multiplier, once
+    pub fn kinds(&self) -> Vec<DamageType> {
+        let mut kinds = vec![];
+
+        for &k in self
+            .resistant
+            .iter()
+            .chain(&self.vulnerable)
+            .chain(&self.immune)
+        {
+            if !kinds.contains(&k) {
+                kinds.push(k);
+            }
+        }
+
+        kinds
+    }
+
+    // resistance and vulnerability to the same type cancel out
+    pub fn multiplier(&self, kind: DamageType) -> f64 {
+        if self.immune.contains(&kind) {
+            return 0.0;
+        }
+
+        let mut m = 1.0;
+
+        if self.resistant.contains(&kind) {
+            m *= 0.5;
+        }
+        if self.vulnerable.contains(&kind) {
+            m *= 2.0;
+        }
+
+        m
+    }
+}
+
+impl Resources {
+    // use up one use of the pool, false if none are left
+    pub fn spend(&mut self, pool: usize) -> bool {
+        match self.pools.get_mut(pool) {
+            Some(p) if p.uses > 0 => {
+                p.uses -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl HuntersMark {
+    pub fn breakeven(&self, ac: i32) -> (f64, i32, f64) {
+        let base = self.unmodified.expected_damage(ac);
+        let first = self.first_turn.expected_damage(ac);
+        let max = self.max_damage.expected_damage(ac);
+
+        (
+            max,
+            1 + ((base - first) / (max - base)).ceil() as i32,
+            first - base,
+        )
+    }
+
+    // total over the given rounds, casting the mark in the first
+    pub fn cumulative_damage(&self, ac: i32, rounds: u32) -> f64 {
+        cumulative_damage(&self.first_turn, &self.max_damage, ac, rounds)
+    }
+
+    // total over the given rounds when taking one hit of incoming damage a
+    // round, each forcing a concentration save to keep the mark up
+    pub fn expected_damage_under_fire(
+        &self,
+        ac: i32,
+        rounds: u32,
+        incoming: f64,
+        con_save: i32,
+    ) -> f64 {
+        let base = self.unmodified.expected_damage(ac);
+        let max = self.max_damage.expected_damage(ac);
+        let keep = concentration_save(incoming, con_save);
+
+        let mut total = 0.0;
+        let mut concentrating = 1.0;
+
+        for round in 0..rounds {
+            total += if round == 0 {
+                self.first_turn.expected_damage(ac)
+            } else {
+                concentrating * max + (1.0 - concentrating) * base
+            };
+
+            concentrating *= keep;
+        }
+
+        total
+    }
+}
+
+impl Damage {
+    // the dice rolled the given times plus fixed. None when some of dmg
+    // wasn't built from dice, or fixed isn't whole 64ths
+    fn pmf(&self, rolls: i32) -> Option<Pmf> {
+        self.lowest(rolls)?;
+
+        let mut dice = self.dice;
+        let mut pmf = Pmf::from([(sixty_fourths(self.fixed as f64)?, 1.0)]);
+
+        if let Some((count, faces)) = self.savage {
+            let once = dice_pmf(count, faces);
+            let i = DIE_FACES.iter().position(|&f| f == faces)?;
+
+            let kept = if dice[i] >= count {
+                // the higher of two rolls in place of those dice
+                dice[i] -= count;
+                higher_of_two(&once)
+            } else {
+                let mut gain = Pmf::new();
+                for (x, p) in &once {
+                    for (y, q) in &once {
+                        *gain.entry((y - x).max(0)).or_default() += p * q;
+                    }
+                }
+                gain
+            };
+
+            for _ in 0..rolls {
+                pmf = convolve(&pmf, &kept);
+            }
+        }
+
+        for ((&count, faces), rerolled) in dice.iter().zip(DIE_FACES).zip(self.rerolled) {
+            for _ in 0..rolls {
+                pmf = convolve(&pmf, &dice_pmf(count - rerolled, faces));
+                pmf = convolve(&pmf, &rerolled_dice_pmf(rerolled, faces));
+            }
+        }
+
+        Some(pmf)
+    }
+}
+
+impl Attack {
+    // the damage of a hit and of a crit, with the chance of each as the
+    // mass of the pmf
+    fn outcome_pmfs(&self, ac: i32) -> Option<(Pmf, Pmf)> {
+        let m = self.crit_multiplier;
+        let floor = sixty_fourths(self.min_hit_damage.unwrap_or(0.0))?;
+
+        let floored = |dmg: Damage| -> Option<Pmf> {
+            Some(dmg.pmf(1)?.into_iter().fold(Pmf::new(), |mut pmf, (k, p)| {
+                *pmf.entry(k.max(floor)).or_default() += p;
+                pmf
+            }))
+        };
+
+        let hit = floored(self.dmg)?;
+        let weapon = self.crit_weapon().dmg;
+        let extra = Damage { fixed: 0, ..weapon }.pmf(m - 1)?;
+        let crit = convolve(
+            &convolve(&convolve(&floored(weapon)?, &extra), &self.crit.pmf(m)?),
+            &self.crit_extra.pmf(1)?,
+        );
+        let nat20 = self.nat20_rider.unwrap_or_default().pmf(1)?;
+
+        let rolls = self.hit_die_rolls();
+        let (mut hits, mut crits) = (Pmf::new(), Pmf::new());
+
+        for natural in 1..=20 {
+            // a 1 is only kept when the reroll is a 1 too
+            let chance = match (self.lucky, natural) {
+                (false, _) => 1.0 / 20.0,
+                (true, 1) => 1.0 / 400.0,
+                (true, _) => 21.0 / 400.0,
+            } / rolls.len() as f64;
+
+            for k in &rolls {
+                let margin = natural + self.hit + k - ac;
+                let is_crit = natural >= self.crit_range;
+
+                if !is_crit && (natural == 1 || margin < 0) {
+                    continue;
+                }
+
+                let degrees = if natural == 1 {
+                    0
+                } else {
+                    sixty_fourths(self.degrees.unwrap_or(0.0) * margin.max(0) as f64)?
+                };
+
+                let mut pmf = shift(if is_crit { &crit } else { &hit }, degrees);
+                if natural == 20 {
+                    pmf = convolve(&pmf, &nat20);
+                }
+
+                mix(if is_crit { &mut crits } else { &mut hits }, &pmf, chance);
+            }
+        }
+
+        Some((hits, crits))
+    }
+}
+
+impl Turn {
+    // chance of each damage total at the AC, lowest first, rolling normally.
+    // the best rider of an exclusive group is picked for what landed. None
+    // when some damage wasn't built from dice
+    pub fn distribution(&self, ac: i32) -> Option<Vec<(f64, f64)>> {
+        Some(
+            self.pmf(ac)?
+                .into_iter()
+                .filter(|(_, p)| *p > 0.0)
+                .map(|(k, p)| (k as f64 / 64.0, p))
+                .collect(),
+        )
+    }
+
+    // expected damage at the AC when no hit deals more than the cap, as
+    // under a ward. the once per turn riders are capped as a hit of their
+    // own, not with the hit they land on, and attacks at other creatures
+    // and saves aren't capped. None when some damage wasn't built from dice
+    pub fn expected_damage_with_hit_cap(&self, ac: i32, cap: i32) -> Option<f64> {
+        let pmf = self.capped_pmf(ac, Some(64 * cap as i64))?;
+
+        Some(pmf.iter().map(|(k, p)| *k as f64 / 64.0 * p).sum())
+    }
+
+    fn pmf(&self, ac: i32) -> Option<Pmf> {
+        self.capped_pmf(ac, None)
+    }
+
+    // pmf with the damage of each hit at most cap, in 64ths
+    fn capped_pmf(&self, ac: i32, cap: Option<i64>) -> Option<Pmf> {
+        let capped = |pmf: Pmf| match cap {
+            Some(cap) => truncate(&pmf, cap),
+            None => pmf,
+        };
+
+        let start = Landed {
+            first: None,
+            first_crit: None,
+            action: false,
+            copies: vec![],
+        };
+        let mut states = BTreeMap::from([(start, Pmf::from([(0, 1.0)]))]);
+
+        let distinct = self.first_hits();
+        let attacks = self
+            .action
+            .iter()
+            .map(|a| (true, a))
+            .chain(self.bonus_action.iter().map(|a| (false, a)));
+
+        for (is_action, a) in attacks {
+            let (hits, crits) = a.outcome_pmfs(ac)?;
+            let id = distinct.iter().position(|d| *d == a).unwrap();
+            let bonus = [
+                a.first_hit_bonus.pmf(1)?,
+                a.first_hit_bonus.pmf(a.crit_multiplier)?,
+            ];
+
+            let mut next = BTreeMap::new();
+            for (state, pmf) in states {
+                let miss = 1.0 - mass(&hits) - mass(&crits);
+                mix(next.entry(state.clone()).or_default(), &pmf, miss);
+
+                for (crit, outcome) in [(false, &hits), (true, &crits)] {
+                    let mut landed = state.clone();
+                    let mut hit = outcome.clone();
+
+                    if !landed.copies.contains(&id) {
+                        landed.copies.push(id);
+                        landed.copies.sort();
+                        hit = convolve(&hit, &bonus[crit as usize]);
+                    }
+                    let dmg = convolve(&pmf, &capped(hit));
+
+                    let rolls = if crit { a.crit_multiplier } else { 1 };
+                    landed.first = landed.first.or(Some(rolls));
+                    landed.first_crit = landed.first_crit.or(crit.then_some(a.crit_multiplier));
+                    landed.action |= is_action;
+
+                    mix(next.entry(landed).or_default(), &dmg, 1.0);
+                }
+            }
+            states = next;
+        }
+
+        for (trigger, a) in &self.triggered {
+            let (hits, crits) = a.outcome_pmfs(ac)?;
+            let bonus = [
+                a.first_hit_bonus.pmf(1)?,
+                a.first_hit_bonus.pmf(a.crit_multiplier)?,
+            ];
+
+            let mut next = BTreeMap::new();
+            for (state, pmf) in states {
+                let made = match trigger {
+                    Trigger::Crit => state.first_crit.is_some(),
+                    Trigger::Hit => state.first.is_some(),
+                    Trigger::ActionsMiss => !state.action,
+                };
+
+                if !made {
+                    mix(next.entry(state).or_default(), &pmf, 1.0);
+                    continue;
+                }
+
+                let miss = 1.0 - mass(&hits) - mass(&crits);
+                mix(next.entry(state.clone()).or_default(), &pmf, miss);
+
+                for (crit, outcome) in [(false, &hits), (true, &crits)] {
+                    let mut landed = state.clone();
+                    if *trigger == Trigger::ActionsMiss {
+                        let rolls = if crit { a.crit_multiplier } else { 1 };
+                        landed.first = landed.first.or(Some(rolls));
+                    }
+
+                    let hit = capped(convolve(outcome, &bonus[crit as usize]));
+                    mix(next.entry(landed).or_default(), &convolve(&pmf, &hit), 1.0);
+                }
+            }
+            states = next;
+        }
+
+        // made when the first action attack landed
+        let cleave = match self.cleave {
+            Some((ac, a)) => Some(alone(a).pmf(ac)?),
+            None => None,
+        };
+        let weapon = self
+            .action
+            .first()
+            .map(|w| distinct.iter().position(|d| *d == w));
+
+        let mut total = Pmf::new();
+        for (state, pmf) in states {
+            let mut pmf = pmf;
+
+            if let (Some(cleave), Some(Some(id))) = (&cleave, weapon) {
+                if state.copies.contains(&id) {
+                    pmf = convolve(&pmf, cleave);
+                }
+            }
+
+            let mut riders = Pmf::from([(0, 1.0)]);
+            for (dmg, rolls) in
+                self.landed_riders(state.first, state.first_crit, &Defenses::default())
+            {
+                riders = convolve(&riders, &dmg.pmf(rolls)?);
+            }
+            pmf = convolve(&pmf, &capped(riders));
+
+            mix(&mut total, &pmf, 1.0);
+        }
+
+        let chance = self.reaction_chance.unwrap_or(1.0);
+        for &a in &self.reaction {
+            let mut taken = Pmf::from([(0, 1.0 - chance)]);
+            mix(
+                &mut taken,
+                &self.reaction_turn(a).capped_pmf(ac, cap)?,
+                chance,
+            );
+
+            total = convolve(&total, &taken);
+        }
+
+        for &(ac, a) in &self.other_targets {
+            total = convolve(&total, &alone(a).pmf(ac)?);
+        }
+
+        for save in &self.saves {
+            let fail = save.fail_chance();
+            let (on_fail, on_save) = match (save.half_on_save, save.evasion) {
+                (true, true) => (0.5, 0.0),
+                (true, false) => (1.0, 0.5),
+                (false, _) => (1.0, 0.0),
+            };
+
+            let dmg = save.dmg.pmf(1)?;
+            let mut taken = Pmf::new();
+            mix(&mut taken, &scale(&dmg, on_fail), fail);
+            mix(&mut taken, &scale(&dmg, on_save), 1.0 - fail);
+
+            total = convolve(&total, &taken);
+        }
+
+        Some(total)
+    }
+
+    // the least damage dealt in at least p percent of rounds at the AC, so
+    // the 10th is a bad round and the 90th a good one
+    pub fn percentile(&self, ac: i32, p: f64) -> Option<f64> {
+        let dist = self.distribution(ac)?;
+
+        let mut seen = 0.0;
+        for &(dmg, chance) in &dist {
+            seen += chance;
+            // slack for the float sum of the chances
+            if seen >= p / 100.0 - 1e-9 {
+                return Some(dmg);
+            }
+        }
+
+        dist.last().map(|(dmg, _)| *dmg)
+    }
+
+    // chance a round at the AC deals at least the threshold, from the
+    // distribution. None when some damage wasn't built from dice
+    pub fn probability_at_least(&self, ac: i32, threshold: f64) -> Option<f64> {
+        Some(
+            self.pmf(ac)?
+                .iter()
+                .filter(|(k, _)| **k as f64 / 64.0 >= threshold)
+                .map(|(_, p)| p)
+                .sum(),
+        )
+    }
+
+    // expected number of action and bonus action attacks, in order, made
+    // after the ones before them already dealt the hp, riders included, at
+    // the AC. None when some damage wasn't built from dice
+    pub fn expected_wasted_attacks(&self, ac: i32, hp: i32) -> Option<f64> {
+        let actions = self.action.len();
+        let mut wasted = 0.0;
+
+        for made in 1..actions + self.bonus_action.len() {
+            let before = Turn {
+                action: self.action[..made.min(actions)].to_vec(),
+                bonus_action: self.bonus_action[..made.saturating_sub(actions)].to_vec(),
+                once_on_hit: self.once_on_hit,
+                riders: self.riders.clone(),
+                ..Default::default()
+            };
+
+            wasted += before.probability_at_least(ac, hp as f64)?;
+        }
+
+        Some(wasted)
+    }
+
+    // how far the damage spreads around its mean at the AC, from the
+    // distribution. None when some damage wasn't built from dice
+    pub fn variance(&self, ac: i32) -> Option<f64> {
+        let dist = self.distribution(ac)?;
+        let mean: f64 = dist.iter().map(|(d, p)| d * p).sum();
+
+        Some(dist.iter().map(|(d, p)| p * (d - mean).powi(2)).sum())
+    }
+
+    pub fn std_dev(&self, ac: i32) -> Option<f64> {
+        self.variance(ac).map(f64::sqrt)
+    }
+}
+
+impl Attack {
+    // the variance of the attack made alone
+    pub fn variance(&self, ac: i32) -> Option<f64> {
+        alone(*self).variance(ac)
+    }
+}
+
+#[cfg(feature = "simulate")]
+impl Damage {
+    // the dice rolled the given times plus fixed, or the averages when dmg
+    // wasn't built from dice
+    fn roll(&self, rolls: i32, rng: &mut impl rand::Rng) -> f64 {
+        if self.lowest(rolls).is_none() {
+            return self.crit_with(rolls);
+        }
+
+        let mut dice = self.dice;
+        let mut total = self.fixed as f64;
+
+        for _ in 0..rolls {
+            if let Some((count, faces)) = self.savage {
+                let (x, y) = (roll_dice(count, faces, rng), roll_dice(count, faces, rng));
+
+                match DIE_FACES.iter().position(|&f| f == faces) {
+                    // the higher of two rolls in place of those dice
+                    Some(i) if self.dice[i] >= count => {
+                        dice[i] = self.dice[i] - count;
+                        total += x.max(y) as f64;
+                    }
+                    _ => total += y.saturating_sub(x) as f64,
+                }
+            }
+
+            for ((&count, faces), rerolled) in dice.iter().zip(DIE_FACES).zip(self.rerolled) {
+                total += roll_dice(count - rerolled, faces, rng) as f64;
+                total += (0..rerolled)
+                    .map(|_| match rng.gen_range(1..=faces) {
+                        1 | 2 => rng.gen_range(1..=faces),
+                        k => k,
+                    })
+                    .sum::<u32>() as f64;
+            }
+        }
+
+        total
+    }
+}
+
+#[cfg(feature = "simulate")]
+fn roll_dice(count: u32, faces: u32, rng: &mut impl rand::Rng) -> u32 {
+    (0..count).map(|_| rng.gen_range(1..=faces)).sum()
+}
+
+// a natural roll of the d20 plus the hit die, if any
+#[cfg(feature = "simulate")]
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Rolled {
+    natural: i32,
+    hit_die: i32,
+}
+
+#[cfg(feature = "simulate")]
+impl Attack {
+    fn roll(&self, rng: &mut impl rand::Rng) -> Rolled {
+        let mut natural = rng.gen_range(1..=20);
+        if self.lucky && natural == 1 {
+            natural = rng.gen_range(1..=20);
+        }
+
+        Rolled {
+            natural,
+            hit_die: self.hit_die.map_or(0, |n| rng.gen_range(1..=n)),
+        }
+    }
+
+    // whether the roll landed and was a crit, and the damage without
+    // first_hit_bonus
+    fn resolve(&self, ac: i32, rolled: Rolled, rng: &mut impl rand::Rng) -> (Option<bool>, f64) {
+        let Rolled { natural, hit_die } = rolled;
+        let margin = natural + self.hit + hit_die - ac;
+
+        let crit = natural >= self.crit_range;
+        let hit = crit || (natural != 1 && margin >= 0);
+
+        if !hit {
+            return (None, 0.0);
+        }
+
+        let m = self.crit_multiplier;
+        let weapon = if crit {
+            self.crit_weapon().dmg
+        } else {
+            self.dmg
+        };
+        let mut dmg = weapon.roll(1, rng);
+        dmg = self.min_hit_damage.map_or(dmg, |min| dmg.max(min));
+
+        if crit {
+            let extra = Damage { fixed: 0, ..weapon };
+            dmg += extra.roll(m - 1, rng) + self.crit.roll(m, rng) + self.crit_extra.roll(1, rng);
+        }
+
+        if natural != 1 {
+            dmg += self.degrees.unwrap_or(0.0) * margin.max(0) as f64;
+        }
+        if natural == 20 {
+            dmg += self.nat20_rider.map_or(0.0, |d| d.roll(1, rng));
+        }
+
+        (Some(crit), dmg)
+    }
+}
+
+#[cfg(feature = "simulate")]
+impl Turn {
+    // sample mean of the turn's damage over the iterations, rolling every d20
+    pub fn simulate(&self, ac: i32, iterations: u32) -> f64 {
+        self.simulate_with(ac, iterations, &mut rand::thread_rng())
+    }
+
+    pub fn simulate_with(&self, ac: i32, iterations: u32, rng: &mut impl rand::Rng) -> f64 {
+        (0..iterations)
+            .map(|_| self.simulate_once(ac, rng))
+            .sum::<f64>()
+            / iterations as f64
+    }
+
+    fn simulate_once(&self, ac: i32, rng: &mut impl rand::Rng) -> f64 {
+        let mut total = 0.0;
+        // how many times the first attack to land rolled its dice, and the
+        // multiplier of the first attack to crit
+        let mut first: Option<i32> = None;
+        let mut first_crit: Option<i32> = None;
+        let mut action_landed = false;
+        let mut landed: Vec<&Attack> = vec![];
+
+        let attacks = self
+            .action
+            .iter()
+            .map(|a| (true, a))
+            .chain(self.bonus_action.iter().map(|a| (false, a)));
+
+        for (is_action, a) in attacks {
+            let rolled = a.roll(rng);
+            let (outcome, dmg) = a.resolve(ac, rolled, rng);
+            total += dmg;
+
+            if let Some(crit) = outcome {
+                let rolls = if crit { a.crit_multiplier } else { 1 };
+
+                if !landed.contains(&a) {
+                    landed.push(a);
+                    total += a.first_hit_bonus.roll(rolls, rng);
+                }
+
+                first = first.or(Some(rolls));
+                first_crit = first_crit.or(crit.then_some(a.crit_multiplier));
+                action_landed |= is_action;
+            }
+        }
+
+        let any_landed = first.is_some();
+
+        if let Some((ac, a)) = self.cleave {
+            if self.action.first().is_some_and(|w| landed.contains(&w)) {
+                total += alone(a).simulate_once(ac, rng);
+            }
+        }
+
+        for (trigger, a) in &self.triggered {
+            let made = match trigger {
+                Trigger::Crit => first_crit.is_some(),
+                Trigger::Hit => any_landed,
+                Trigger::ActionsMiss => !action_landed,
+            };
+
+            if made {
+                let rolled = a.roll(rng);
+                let (outcome, dmg) = a.resolve(ac, rolled, rng);
+                total += dmg;
+
+                if let Some(crit) = outcome {
+                    let rolls = if crit { a.crit_multiplier } else { 1 };
+                    total += a.first_hit_bonus.roll(rolls, rng);
+
+                    if *trigger == Trigger::ActionsMiss {
+                        first = first.or(Some(rolls));
+                    }
+                }
+            }
+        }
+
+        for &a in &self.reaction {
+            if rng.gen_bool(self.reaction_chance.unwrap_or(1.0)) {
+                total += self.reaction_turn(a).simulate_once(ac, rng);
+            }
+        }
+
+        for &(ac, a) in &self.other_targets {
+            total += alone(a).simulate_once(ac, rng);
+        }
+
+        for save in &self.saves {
+            let failed = rng.gen_range(1..=20) + save.save_bonus_of_target < save.dc;
+
+            let taken = match (failed, save.half_on_save, save.evasion) {
+                (true, true, true) => 0.5,
+                (true, _, _) => 1.0,
+                (false, true, false) => 0.5,
+                (false, _, _) => 0.0,
+            };
+
+            total += taken * save.dmg.roll(1, rng);
+        }
+
+        // riders, the best of each exclusive group for what landed
+        total
+            + self
+                .landed_riders(first, first_crit, &Defenses::default())
+                .iter()
+                .map(|(d, rolls)| d.roll(*rolls, rng))
+                .sum::<f64>()
+    }
+}
+
+// --- Trait Methods ---
+
+use core::num::{IntErrorKind, ParseIntError};
+use core::ops::{Add, RangeInclusive};
+use core::str::FromStr;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+impl Add for Damage {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        let mut typed = self.typed;
+        for (t, (dmg, fixed)) in typed.iter_mut().zip(other.typed) {
+            *t = (t.0 + dmg, t.1 + fixed);
+        }
+
+        let mut dice = self.dice;
+        for (d, count) in dice.iter_mut().zip(other.dice) {
+            *d += count;
+        }
+
+        let mut rerolled = self.rerolled;
+        for (r, count) in rerolled.iter_mut().zip(other.rerolled) {
+            *r += count;
+        }
+
+        Self {
+            dmg: self.dmg + other.dmg,
+            fixed: self.fixed + other.fixed,
+            typed,
+            dice,
+            rerolled,
+            // only one reroll is kept track of
+            savage: self.savage.or(other.savage),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Malformed(term) => write!(f, "malformed dice term: {term:?}"),
+            ParseError::UnknownDie(faces) => write!(f, "no such die: d{faces}"),
+            ParseError::OutOfRange(term) => write!(f, "number out of range: {term:?}"),
+            ParseError::UnknownAttack(name) => write!(f, "unknown attack: {name}"),
+            ParseError::Cycle(name) => write!(f, "attack is built from itself: {name}"),
+            ParseError::Config(e) => write!(f, "bad config: {e}"),
+            ParseError::Io(e) => write!(f, "can't read config: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// a number in the dice term
+fn term_number<T: FromStr<Err = ParseIntError>>(n: &str, term: &str) -> Result<T, ParseError> {
+    n.parse().map_err(|e: ParseIntError| match e.kind() {
+        IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => {
+            ParseError::OutOfRange(term.to_string())
+        }
+        _ => ParseError::Malformed(term.to_string()),
+    })
+}
+
+// a die's faces, at least 2
+fn die_faces(faces: u32) -> Result<u32, ParseError> {
+    if faces < 2 {
+        return Err(ParseError::UnknownDie(faces));
+    }
+
+    Ok(faces)
+}
+
+// dice notation, e.g. 1d8+1d4+2. only flat modifiers may be subtracted
+impl FromStr for Damage {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let text: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+
+        // signed terms, a leading sign belongs to the first
+        let mut terms = vec![];
+        let (mut sign, mut start) = (1, 0);
+        for (i, c) in text.char_indices() {
+            if c == '+' || c == '-' {
+                if i > 0 {
+                    terms.push((sign, &text[start..i]));
+                }
+                sign = if c == '-' { -1 } else { 1 };
+                start = i + 1;
+            }
+        }
+        terms.push((sign, &text[start..]));
+
+        let mut dmg = Damage::default();
+
+        for (sign, term) in terms {
+            match term.split_once('d') {
+                Some((count, faces)) => {
+                    let count = if count.is_empty() {
+                        1
+                    } else {
+                        term_number(count, term)?
+                    };
+                    let faces = die_faces(term_number(faces, term)?)?;
+
+                    if sign < 0 {
+                        return Err(ParseError::Malformed(format!("-{term}")));
+                    }
+
+                    dmg = dmg
+                        .checked_add(Damage::dice(count, faces))
+                        .ok_or(ParseError::OutOfRange(term.to_string()))?;
+                }
+                None => {
+                    let k: i32 = term_number(term, term)?;
+
+                    dmg.fixed = dmg
+                        .fixed
+                        .checked_add(sign * k)
+                        .ok_or(ParseError::OutOfRange(term.to_string()))?;
+                }
+            }
+        }
+
+        Ok(dmg)
+    }
+}
+
+impl Damage {
+    // self + other, None when a count of dice would overflow
+    fn checked_add(self, other: Damage) -> Option<Damage> {
+        for (a, b) in self.dice.iter().zip(other.dice) {
+            a.checked_add(b)?;
+        }
+
+        Some(self + other)
+    }
+}
+
+#[cfg(feature = "config")]
+impl Attack {
+    fn checked_add(self, other: Attack) -> Option<Attack> {
+        self.dmg.checked_add(other.dmg)?;
+        self.crit.checked_add(other.crit)?;
+        self.crit_extra.checked_add(other.crit_extra)?;
+        self.first_hit_bonus.checked_add(other.first_hit_bonus)?;
+        self.nat20_rider
+            .unwrap_or_default()
+            .checked_add(other.nat20_rider.unwrap_or_default())?;
+
+        Some(self + other)
+    }
+}
+
+impl Default for Attack {
+    fn default() -> Self {
+        Attack {
+            hit: 0,
+            dmg: Damage::default(),
+            crit: Damage::default(),
+            crit_extra: Damage::default(),
+            dmg_on_crit: None,
+            first_hit_bonus: Damage::default(),
+            degrees: None,
+            min_hit_damage: None,
+            nat20_rider: None,
+            crit_range: 20,
+            hit_die: None,
+            crit_multiplier: 2,
+            lucky: false,
+        }
+    }
+}
+
+impl Add for Attack {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Attack {
+            hit: self.hit + other.hit,
+            dmg: self.dmg + other.dmg,
+            crit: self.crit + other.crit,
+            crit_extra: self.crit_extra + other.crit_extra,
+            // what's added to dmg is rolled on a crit too
+            dmg_on_crit: match (self.dmg_on_crit, other.dmg_on_crit) {
+                (None, None) => None,
+                (a, b) => Some(a.unwrap_or(self.dmg) + b.unwrap_or(other.dmg)),
+            },
+            first_hit_bonus: self.first_hit_bonus + other.first_hit_bonus,
+            degrees: match (self.degrees, other.degrees) {
+                (Some(a), Some(b)) => Some(a + b),
+                (a, b) => a.or(b),
+            },
+            min_hit_damage: match (self.min_hit_damage, other.min_hit_damage) {
+                (Some(a), Some(b)) => Some(a.max(b)),
+                (a, b) => a.or(b),
+            },
+            nat20_rider: match (self.nat20_rider, other.nat20_rider) {
+                (Some(a), Some(b)) => Some(a + b),
+                (a, b) => a.or(b),
+            },
+            // the wider range applies
+            crit_range: self.crit_range.min(other.crit_range),
+            // only the bigger die is kept
+            hit_die: self.hit_die.max(other.hit_die),
+            crit_multiplier: self.crit_multiplier.max(other.crit_multiplier),
+            lucky: self.lucky || other.lucky,
+        }
+    }
+}
+
+impl Add<Damage> for Attack {
+    type Output = Self;
+
+    fn add(self, dmg: Damage) -> Self {
+        Attack {
+            dmg: self.dmg + dmg,
+            dmg_on_crit: self.dmg_on_crit.map(|d| d + dmg),
+            ..self
+        }
+    }
+}
+
+impl Add<Attack> for Turn {
+    type Output = Self;
+
+    fn add(self, atk: Attack) -> Self {
+        Turn {
+            action: self.action.clone().into_iter().map(|a| a + atk).collect(),
+            bonus_action: self
+                .bonus_action
+                .clone()
+                .into_iter()
+                .map(|a| a + atk)
+                .collect(),
+            reaction: self.reaction.clone().into_iter().map(|a| a + atk).collect(),
+            reaction_chance: self.reaction_chance,
+            other_targets: self
+                .other_targets
+                .iter()
+                .map(|&(ac, a)| (ac, a + atk))
+                .collect(),
+            cleave: self.cleave.map(|(ac, a)| (ac, a + atk)),
+            once_on_hit: self.once_on_hit,
+            riders: self.riders.clone(),
+            triggered: self.triggered.iter().map(|&(t, a)| (t, a + atk)).collect(),
+            saves: self.saves.clone(),
+            name: self.name.clone(),
+        }
+    }
+}
+
+impl Add<Damage> for Turn {
+    type Output = Self;
+
+    fn add(self, dmg: Damage) -> Self {
+        Turn {
+            action: self.action.clone().into_iter().map(|a| a + dmg).collect(),
+            bonus_action: self
+                .bonus_action
+                .clone()
+                .into_iter()
+                .map(|a| a + dmg)
+                .collect(),
+            reaction: self.reaction.clone().into_iter().map(|a| a + dmg).collect(),
+            reaction_chance: self.reaction_chance,
+            // damage against the target, like Hunter's Mark
+            other_targets: self.other_targets.clone(),
+            cleave: self.cleave,
+            once_on_hit: self.once_on_hit,
+            riders: self.riders.clone(),
+            triggered: self.triggered.iter().map(|&(t, a)| (t, a + dmg)).collect(),
+            saves: self.saves.clone(),
+            name: self.name.clone(),
+        }
+    }
+}
+
+impl SpendPolicy for Greedy {
+    fn choose(&mut self, resources: &Resources, ac: i32, _round: u32) -> Option<usize> {
+        resources
+            .pools
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.uses > 0)
+            .max_by(|(_, a), (_, b)| {
+                a.turn
+                    .expected_damage(ac)
+                    .total_cmp(&b.turn.expected_damage(ac))
+            })
+            .map(|(i, _)| i)
+    }
+}
+
+impl ExpectedDamage for Attack {
+    // a lone attack is always the first copy to land
+    fn expected_damage_with(&self, ac: i32, roll: Roll) -> f64 {
+        self.base_damage(ac, roll) + self.first_hit_damage(ac, roll)
+    }
+
+    // degrees and the min_hit_damage floor are untyped
+    fn of_type(&self, kind: DamageType) -> Attack {
+        Attack {
+            dmg: self.dmg.of_type(kind),
+            crit: self.crit.of_type(kind),
+            crit_extra: self.crit_extra.of_type(kind),
+            dmg_on_crit: self.dmg_on_crit.map(|d| d.of_type(kind)),
+            first_hit_bonus: self.first_hit_bonus.of_type(kind),
+            degrees: None,
+            min_hit_damage: None,
+            nat20_rider: self.nat20_rider.map(|d| d.of_type(kind)),
+            ..*self
+        }
+    }
+}
+
+// no attack roll, so AC and the roll don't matter
+impl ExpectedDamage for SaveAttack {
+    fn expected_damage_with(&self, _ac: i32, _roll: Roll) -> f64 {
+        let fail = self.fail_chance();
+
+        let (on_fail, on_save) = match (self.half_on_save, self.evasion) {
+            (false, _) => (1.0, 0.0),
+            (true, false) => (1.0, 0.5),
+            (true, true) => (0.5, 0.0),
+        };
+
+        (fail * on_fail + (1.0 - fail) * on_save) * self.dmg.hit()
+    }
+
+    // every target in the area saves, so these are their save bonuses
+    fn expected_damage_multi(&self, targets: &[i32]) -> f64 {
+        targets
+            .iter()
+            .map(|&bonus| {
+                SaveAttack {
+                    save_bonus_of_target: bonus,
+                    ..*self
+                }
+                .expected_damage(0)
+            })
+            .sum()
+    }
+
+    fn of_type(&self, kind: DamageType) -> SaveAttack {
+        SaveAttack {
+            dmg: self.dmg.of_type(kind),
+            ..*self
+        }
+    }
+}
+
+impl ExpectedDamage for Turn {
+    fn expected_damage_with(&self, ac: i32, roll: Roll) -> f64 {
+        self.contributions(|_| ac, |_| roll)
+            .iter()
+            .map(|(_, d)| d)
+            .sum()
+    }
+
+    // the riders apart, so each exclusive group picks its best rider after
+    // the defenses
+    fn expected_damage_vs(&self, ac: i32, defenses: &Defenses) -> f64 {
+        let bare = Turn {
+            once_on_hit: Damage::default(),
+            riders: vec![],
+            ..self.clone()
+        };
+
+        let chance = self.reaction_chance.unwrap_or(1.0);
+        let reactions: f64 = self
+            .reaction
+            .iter()
+            .map(|&a| {
+                chance
+                    * self
+                        .reaction_turn(a)
+                        .rider_damage(|_| ac, |_| Roll::Normal, defenses)
+            })
+            .sum();
+
+        defenses
+            .kinds()
+            .iter()
+            .map(|&k| (defenses.multiplier(k) - 1.0) * bare.of_type(k).expected_damage(ac))
+            .sum::<f64>()
+            + bare.expected_damage(ac)
+            + self.rider_damage(|_| ac, |_| Roll::Normal, defenses)
+            + reactions
+    }
+
+    fn of_type(&self, kind: DamageType) -> Turn {
+        Turn {
+            action: self.action.iter().map(|a| a.of_type(kind)).collect(),
+            bonus_action: self.bonus_action.iter().map(|a| a.of_type(kind)).collect(),
+            reaction: self.reaction.iter().map(|a| a.of_type(kind)).collect(),
+            reaction_chance: self.reaction_chance,
+            other_targets: self
+                .other_targets
+                .iter()
+                .map(|&(ac, a)| (ac, a.of_type(kind)))
+                .collect(),
+            cleave: self.cleave.map(|(ac, a)| (ac, a.of_type(kind))),
+            once_on_hit: self.once_on_hit.of_type(kind),
+            riders: self
+                .riders
+                .iter()
+                .map(|r| OncePerTurnRider {
+                    dmg: r.dmg.of_type(kind),
+                    ..r.clone()
+                })
+                .collect(),
+            triggered: self
+                .triggered
+                .iter()
+                .map(|&(t, a)| (t, a.of_type(kind)))
+                .collect(),
+            saves: self.saves.iter().map(|s| s.of_type(kind)).collect(),
+            name: self.name.clone(),
+        }
+    }
+}
+
+// --- Functions ---
+
+// index of the enemy where a nova turn adds the most over the sustained turn,
+// only counting damage up to the enemy's hp. None when there are no enemies
+pub fn best_nova_target(sustained: &Turn, nova: &Turn, enemies: &[Monster]) -> Option<usize> {
+    let gain = |m: &Monster| {
+        let hp = m.hp as f64;
+
+        nova.expected_damage(m.ac).min(hp) - sustained.expected_damage(m.ac).min(hp)
+    };
+
+    enemies
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| gain(a).total_cmp(&gain(b)))
+        .map(|(i, _)| i)
+}
+
+// expected damage over the rounds, taking the sustained turn whenever the
+// policy spends nothing or picks an empty pool
+pub fn encounter_damage(
+    sustained: &Turn,
+    resources: &mut Resources,
+    policy: &mut impl SpendPolicy,
+    ac: i32,
+    rounds: u32,
+) -> f64 {
+    (0..rounds)
+        .map(|round| match policy.choose(resources, ac, round) {
+            Some(i) if resources.spend(i) => resources.pools[i].turn.expected_damage(ac),
+            _ => sustained.expected_damage(ac),
+        })
+        .sum()
+}
+
+// expected damage over the rounds, the first turn and then the sustained turn
+// each round after, e.g. an Action Surge nova or casting Hunter's Mark
+pub fn cumulative_damage(first: &Turn, sustained: &Turn, ac: i32, rounds: u32) -> f64 {
+    match rounds {
+        0 => 0.0,
+        _ => first.expected_damage(ac) + (rounds - 1) as f64 * sustained.expected_damage(ac),
+    }
+}
+
+// expected damage for each (turn, ac) query, evaluating each distinct
+// turn only once per AC. turns hold floats, so they're keyed by their Debug
+// text, which tells apart any two that differ
+pub fn batch_expected_damage(queries: &[(Turn, i32)]) -> Vec<f64> {
+    let mut cache: HashMap<(String, i32), f64> = HashMap::new();
+
+    queries
+        .iter()
+        .map(|(turn, ac)| {
+            *cache
+                .entry((format!("{turn:?}"), *ac))
+                .or_insert_with(|| turn.expected_damage(*ac))
+        })
+        .collect()
+}
+
+// names of the builds no other build matches or beats at both ACs while
+// beating it at one of them
+pub fn pareto_frontier(turns: &[(String, Turn)], ac_low: i32, ac_high: i32) -> Vec<String> {
+    let dmgs: Vec<(f64, f64)> = turns
+        .iter()
+        .map(|(_, t)| (t.expected_damage(ac_low), t.expected_damage(ac_high)))
+        .collect();
+
+    let dominated = |(low, high): (f64, f64)| {
+        dmgs.iter()
+            .any(|&(l, h)| l >= low && h >= high && (l > low || h > high))
+    };
+
+    turns
+        .iter()
+        .zip(&dmgs)
+        .filter(|(_, &d)| !dominated(d))
+        .map(|((name, _), _)| name.clone())
+        .collect()
+}
+
+// Booming Blade's thunder damage on a hit, which the crit doubles. the
+// extra damage when the target moves is left out
+pub fn booming_blade(weapon: Attack, level: u32) -> Attack {
+    let dice = match level {
+        1..=4 => 0,
+        5..=10 => 1,
+        11..=16 => 2,
+        _ => 3,
+    };
+
+    weapon + Damage::dice(dice, 8).of(DamageType::Thunder)
+}
+
+// an unarmed monk: dex 16 raised at 4th and 8th, Martial Arts die, Extra
+// Attack at 5th, and Flurry of Blows from 2nd
+pub fn build_at_level(level: u32) -> Turn {
+    let proficiency = 2 + (level as i32 - 1) / 4;
+    let dex = match level {
+        1..=3 => 3,
+        4..=7 => 4,
+        _ => 5,
+    };
+    let martial_arts = match level {
+        1..=4 => 4,
+        5..=10 => 6,
+        11..=16 => 8,
+        _ => 10,
+    };
+
+    let unarmed = Attack {
+        hit: dex + proficiency,
+        dmg: Damage {
+            fixed: dex,
+            ..Damage::dice(1, martial_arts)
+        }
+        .of(DamageType::Bludgeoning),
+        ..Default::default()
+    };
+
+    Turn {
+        action: vec![unarmed; if level >= 5 { 2 } else { 1 }],
+        bonus_action: vec![unarmed; if level >= 2 { 2 } else { 1 }],
+        name: format!("monk {}", level),
+        ..Default::default()
+    }
+}
+
+// expected damage at each level from 1 to 20
+pub fn dpr_by_level(build: impl Fn(u32) -> Turn, ac: i32) -> Vec<(u32, f64)> {
+    (1..=20)
+        .map(|level| (level, build(level).expected_damage(ac)))
+        .collect()
+}
+
+// flat damage bonus per attack on a that matches b's expected damage. fixed
+// damage isn't doubled on a crit, so each point is worth the expected number
+// of attacks that land
+pub fn balancing_bonus(a: &Turn, b: &Turn, ac: i32) -> f64 {
+    let base = a.expected_damage(ac);
+    let per_point = (a.clone()
+        + Damage {
+            fixed: 1,
+            ..Default::default()
+        })
+    .expected_damage(ac)
+        - base;
+
+    (b.expected_damage(ac) - base) / per_point
+}
+
+// chance a deals more damage than b in a round at the AC, the same, and
+// less, from the turns' distributions. None when some damage wasn't built
+// from dice
+pub fn head_to_head(a: &Turn, b: &Turn, ac: i32) -> Option<(f64, f64, f64)> {
+    let (a, b) = (a.pmf(ac)?, b.pmf(ac)?);
+
+    let (mut wins, mut ties) = (0.0, 0.0);
+    for (x, p) in &a {
+        wins += p * b.range(..x).map(|(_, q)| q).sum::<f64>();
+        ties += p * b.get(x).unwrap_or(&0.0);
+    }
+
+    Some((wins, ties, 1.0 - wins - ties))
+}
+
+// chance to keep concentration when hit, DC 10 or half the damage taken
+pub fn concentration_save(damage: f64, con_save: i32) -> f64 {
+    let dc = 10.max((damage / 2.0) as i32);
+
+    (21 + con_save - dc).clamp(0, 20) as f64 / 20.0
+}
+
+// AC of a monster at the challenge rating, weighted one point either side
+// of the DMG's Monster Statistics by Challenge Rating table
+pub fn cr_ac_distribution(cr: f64) -> [(i32, f64); 3] {
+    let ac = match cr {
+        cr if cr < 4.0 => 13,
+        cr if cr < 5.0 => 14,
+        cr if cr < 8.0 => 15,
+        cr if cr < 10.0 => 16,
+        cr if cr < 13.0 => 17,
+        cr if cr < 17.0 => 18,
+        _ => 19,
+    };
+
+    [(ac - 1, 0.25), (ac, 0.5), (ac + 1, 0.25)]
+}
+
+// the builds compared by default
+pub fn default_builds() -> Vec<Turn> {
+    // attack base
+    let dex = Attack::builder().hit(5).fixed(5).build();
+    let proficiency_bonus = Attack::builder().hit(4).build();
+
+    let monk = dex + proficiency_bonus;
+
+    // attack modifiers
+    let archery = Attack::builder().hit(2).build();
+    let deft_strike = Attack::builder().crit_dice(1, 6).build();
+    let plusone = Attack::builder().hit(1).fixed(1).build();
+
+    // weapons
+    let crossbow = Attack::builder().dice(1, 6).build();
+    let longsword = Attack::builder().dice(1, 10).build();
+    let unarmed = Attack::builder().dice(1, 6).build();
+
+    // attacks
+    let crossbow = monk + archery + crossbow + plusone + deft_strike;
+    let sharp = crossbow.sharpshooter();
+    let longsword = monk + longsword + plusone + deft_strike;
+    let unarmed = monk + unarmed;
+
+    // turns
+    let crossbow = Turn {
+        action: vec![crossbow; 2],
+        name: "xbow".to_string(),
+        ..Default::default()
+    }
+    .crossbow_expert(crossbow);
+
+    let sharp = Turn {
+        action: vec![sharp; 2],
+        name: "sharp xbow".to_string(),
+        ..Default::default()
+    }
+    .crossbow_expert(sharp);
+
+    let melee = Turn {
+        action: vec![longsword; 2],
+        bonus_action: vec![unarmed; 2],
+        name: "sword/flurry".to_string(),
+        ..Default::default()
+    };
+
+    vec![crossbow, sharp, melee]
+}
+
+// SVG chart with a damage vs AC line per build
+#[cfg(feature = "plot")]
+pub fn plot_dpr(
+    turns: &[Turn],
+    range: RangeInclusive<i32>,
+    path: &std::path::Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use plotters::prelude::*;
+
+    let max = turns
+        .iter()
+        .flat_map(|t| range.clone().map(|ac| t.expected_damage(ac)))
+        .fold(0.0, f64::max);
+
+    let root = SVGBackend::new(path, (800, 600)).into_drawing_area();
+    root.fill(&WHITE)?;
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption("damage per round", ("sans-serif", 20))
+        .margin(10)
+        .x_label_area_size(30)
+        .y_label_area_size(40)
+        .build_cartesian_2d(*range.start()..*range.end(), 0.0..max * 1.1)?;
+
+    chart.configure_mesh().x_desc("AC").y_desc("DPR").draw()?;
+
+    for (i, t) in turns.iter().enumerate() {
+        let color = Palette99::pick(i).to_rgba();
+
+        chart
+            .draw_series(LineSeries::new(
+                range.clone().map(|ac| (ac, t.expected_damage(ac))),
+                color.stroke_width(2),
+            ))?
+            .label(t.name.as_str())
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+    }
+
+    chart
+        .configure_series_labels()
+        .background_style(WHITE)
+        .border_style(BLACK)
+        .draw()?;
+
+    root.present()?;
+
+    Ok(())
+}
+
+// --- Config ---
+
+// an attack in a config file, the sum of the attacks it is built `from` plus
+// its own fields. dice are (count, faces) pairs
+#[cfg(feature = "config")]
+#[derive(Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AttackSpec {
+    from: Vec<String>,
+    hit: i32,
+    dice: Vec<(u32, u32)>,
+    fixed: i32,
+    crit_dice: Vec<(u32, u32)>,
+    crit_range: Option<i32>,
+    sharpshooter: bool,
+    great_weapon_master: bool,
+}
+
+// a named turn of attacks, by name
+#[cfg(feature = "config")]
+#[derive(Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct TurnSpec {
+    name: String,
+    action: Vec<String>,
+    bonus_action: Vec<String>,
+    reaction: Vec<String>,
+    reaction_chance: Option<f64>,
+}
+
+#[cfg(feature = "config")]
+#[derive(Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Config {
+    attacks: std::collections::BTreeMap<String, AttackSpec>,
+    turns: Vec<TurnSpec>,
+}
+
+#[cfg(feature = "config")]
+impl Config {
+    fn attack(&self, name: &str, seen: &mut Vec<String>) -> Result<Attack, ParseError> {
+        let spec = self
+            .attacks
+            .get(name)
+            .ok_or(ParseError::UnknownAttack(name.to_string()))?;
+
+        if seen.iter().any(|s| s == name) {
+            return Err(ParseError::Cycle(name.to_string()));
+        }
+        seen.push(name.to_string());
+
+        let too_many = || ParseError::OutOfRange(format!("dice of {name}"));
+
+        let mut atk = Attack::default();
+        for part in &spec.from {
+            atk = atk
+                .checked_add(self.attack(part, seen)?)
+                .ok_or_else(too_many)?;
+        }
+        seen.pop();
+
+        let crit_range = spec.crit_range.unwrap_or(20);
+        if !(2..=20).contains(&crit_range) {
+            return Err(ParseError::OutOfRange(format!("crit_range = {crit_range}")));
+        }
+
+        let mut own = Attack::builder()
+            .hit(spec.hit)
+            .fixed(spec.fixed)
+            .crit_range(crit_range)
+            .build();
+        for &(count, faces) in &spec.dice {
+            let dice = Damage::dice(count, die_faces(faces)?);
+            own.dmg = own.dmg.checked_add(dice).ok_or_else(too_many)?;
+        }
+        for &(count, faces) in &spec.crit_dice {
+            let dice = Damage::dice(count, die_faces(faces)?);
+            own.crit = own.crit.checked_add(dice).ok_or_else(too_many)?;
+        }
+        atk = atk.checked_add(own).ok_or_else(too_many)?;
+
+        if spec.sharpshooter {
+            atk = atk.sharpshooter();
+        }
+        if spec.great_weapon_master {
+            atk = atk.great_weapon_master();
+        }
+
+        Ok(atk)
+    }
+}
+
+// the turns described by a TOML config, see builds.toml
+#[cfg(feature = "config")]
+pub fn builds_from_toml(text: &str) -> Result<Vec<Turn>, ParseError> {
+    let config: Config = toml::from_str(text).map_err(|e| {
+        ParseError::Config(match e.span() {
+            Some(span) => format!(
+                "line {}: {}",
+                text[..span.start].matches('\n').count() + 1,
+                e.message()
+            ),
+            None => e.message().to_string(),
+        })
+    })?;
+
+    if config.turns.is_empty() {
+        return Err(ParseError::Config("no turns to compare".to_string()));
+    }
+
+    let attacks = |names: &[String]| -> Result<Vec<Attack>, ParseError> {
+        names
+            .iter()
+            .map(|n| config.attack(n, &mut vec![]))
+            .collect()
+    };
+
+    config
+        .turns
+        .iter()
+        .map(|t| {
+            if let Some(chance) = t.reaction_chance.filter(|c| !(0.0..=1.0).contains(c)) {
+                return Err(ParseError::OutOfRange(format!(
+                    "reaction_chance = {chance}"
+                )));
+            }
+
+            Ok(Turn {
+                action: attacks(&t.action)?,
+                bonus_action: attacks(&t.bonus_action)?,
+                reaction: attacks(&t.reaction)?,
+                reaction_chance: t.reaction_chance,
+                name: t.name.clone(),
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+// the turns of a TOML config file
+#[cfg(feature = "config")]
+pub fn load_turns(path: impl AsRef<std::path::Path>) -> Result<Vec<Turn>, ParseError> {
+    let text = std::fs::read_to_string(path).map_err(|e| ParseError::Io(e.to_string()))?;
+
+    builds_from_toml(&text)
+}
+
+// --- Util ---
+pub trait Convert2Cmp {
+    fn cmpable(&self) -> i32;
+}
+
+impl Convert2Cmp for f64 {
+    fn cmpable(&self) -> i32 {
+        (100.0 * *self).trunc() as i32
+    }
+}
+
+pub fn uncmp(x: i32) -> f64 {
+    x as f64 / 100.0
+}
+
+// the AC, going up the range, past which power stops being at least as good
+// as plain
+fn threshold(
+    plain: &impl ExpectedDamage,
+    power: &impl ExpectedDamage,
+    range: RangeInclusive<i32>,
+) -> Option<i32> {
+    range
+        .take_while(|&ac| power.expected_damage(ac) >= plain.expected_damage(ac))
+        .last()
+}
+
+fn sixty_fourths(x: f64) -> Option<i64> {
+    let n = x * 64.0;
+    (n.fract() == 0.0).then_some(n as i64)
+}
+
+fn convolve(a: &Pmf, b: &Pmf) -> Pmf {
+    let mut out = Pmf::new();
+    for (x, p) in a {
+        for (y, q) in b {
+            *out.entry(x + y).or_default() += p * q;
+        }
+    }
+
+    out
+}
+
+// the total of count dice of the faces
+fn dice_pmf(count: u32, faces: u32) -> Pmf {
+    let die: Pmf = (1..=faces as i64)
+        .map(|k| (64 * k, 1.0 / faces as f64))
+        .collect();
+
+    (0..count).fold(Pmf::from([(0, 1.0)]), |pmf, _| convolve(&pmf, &die))
+}
+
+// the total of count dice of the faces, each rerolling a 1 or 2 once
+fn rerolled_dice_pmf(count: u32, faces: u32) -> Pmf {
+    let f = faces as f64;
+    let die: Pmf = (1..=faces as i64)
+        .map(|k| (64 * k, if k >= 3 { 1.0 / f } else { 0.0 } + 2.0 / (f * f)))
+        .collect();
+
+    (0..count).fold(Pmf::from([(0, 1.0)]), |pmf, _| convolve(&pmf, &die))
+}
+
+fn higher_of_two(pmf: &Pmf) -> Pmf {
+    let mut out = Pmf::new();
+    let mut below = 0.0;
+    for (k, p) in pmf {
+        let at_most = below + p;
+        out.insert(*k, at_most * at_most - below * below);
+        below = at_most;
+    }
+
+    out
+}
+
+// damage totals over the cap lowered to it
+fn truncate(pmf: &Pmf, cap: i64) -> Pmf {
+    pmf.iter().fold(Pmf::new(), |mut out, (k, p)| {
+        *out.entry(*k.min(&cap)).or_default() += p;
+        out
+    })
+}
+
+fn shift(pmf: &Pmf, by: i64) -> Pmf {
+    pmf.iter().map(|(k, p)| (k + by, *p)).collect()
+}
+
+// the damage totals multiplied by a factor, exact for halves
+fn scale(pmf: &Pmf, factor: f64) -> Pmf {
+    let mut out = Pmf::new();
+    for (k, p) in pmf {
+        *out.entry((*k as f64 * factor) as i64).or_default() += p;
+    }
+
+    out
+}
+
+fn mix(into: &mut Pmf, pmf: &Pmf, chance: f64) {
+    for (k, p) in pmf {
+        *into.entry(*k).or_default() += chance * p;
+    }
+}
+
+// the attack as the only one of a turn
+fn alone(atk: Attack) -> Turn {
+    Turn {
+        action: vec![atk],
+        ..Default::default()
+    }
+}
+
+fn mass(pmf: &Pmf) -> f64 {
+    pmf.values().sum()
+}
+
+// the chances after the attack is made, its crit counted for the crit_only
+// riders unless it's a triggered attack
+fn land(chances: Chances, atk: &Attack, ac: i32, roll: Roll, action: bool, crits: bool) -> Chances {
+    let hit = atk.hit_chance(ac, roll);
+    let crit = atk.crit_chance(roll);
+    let m = atk.crit_multiplier;
+
+    let mut out = Chances::new();
+    for ((first, first_crit, acted), p) in chances {
+        *out.entry((first, first_crit, acted)).or_default() += p * (1.0 - hit - crit);
+        *out.entry((first.or(Some(1)), first_crit, acted || action))
+            .or_default() += p * hit;
+
+        let first_crit = if crits {
+            first_crit.or(Some(m))
+        } else {
+            first_crit
+        };
+        *out.entry((first.or(Some(m)), first_crit, acted || action))
+            .or_default() += p * crit;
+    }
+
+    out
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// --- Tests ---
+
+#[cfg(test)]
+#[allow(clippy::redundant_field_names)]
+mod tests {
+    use core::str::FromStr;
+
+    use crate::balancing_bonus;
+    use crate::batch_expected_damage;
+    use crate::best_nova_target;
+    use crate::booming_blade;
+    use crate::build_at_level;
+    #[cfg(feature = "config")]
+    use crate::builds_from_toml;
+    use crate::concentration_save;
+    use crate::cumulative_damage;
+    use crate::d10;
+    use crate::d12;
+    use crate::d4;
+    use crate::d6;
+    use crate::d8;
+    use crate::default_builds;
+    use crate::die_ev;
+    use crate::dpr_by_level;
+    use crate::encounter_damage;
+    use crate::great_weapon_fighting_value;
+    use crate::head_to_head;
+    #[cfg(feature = "config")]
+    use crate::load_turns;
+    use crate::pareto_frontier;
+    use crate::savage_attacker_value;
+    use crate::Attack;
+    use crate::Convert2Cmp;
+    use crate::Damage;
+    use crate::DamageType;
+    use crate::Defenses;
+    use crate::DicePolicy;
+    use crate::ExpectedDamage;
+    use crate::Greedy;
+    use crate::Monster;
+    use crate::OncePerTurnRider;
+    use crate::Overkill;
+    use crate::ParseError;
+    use crate::Pool;
+    use crate::Resources;
+    use crate::Roll;
+    use crate::SaveAttack;
+    use crate::SpendPolicy;
+    use crate::Stacking;
+    use crate::Trigger;
+    use crate::Turn;
+    use crate::WeaponMastery;
+
+    // Dice
+
+    #[test]
+    fn test_dice() {
+        assert_eq!(d4, 2.5);
+        assert_eq!(d10, 5.5);
+    }
+
+    // Damage
+
+    #[test]
+    fn test_die_ev() {
+        assert_eq!(die_ev(20), 10.5);
+        assert_eq!(die_ev(100), 50.5);
+        assert_eq!(die_ev(3), 2.0);
+
+        // the named dice still match
+        assert_eq!([d4, d6, d8, d10, d12], [2.5, 3.5, 4.5, 5.5, 6.5]);
+        assert_eq!(die_ev(12), d12);
+    }
+
+    #[test]
+    fn test_dmg_from_str() {
+        assert_eq!(
+            Damage::from_str("2d6+3"),
+            Ok(Damage {
+                fixed: 3,
+                ..Damage::dice(2, 6)
+            })
+        );
+        assert_eq!("1d12".parse::<Damage>().unwrap().dmg, d12);
+        assert_eq!(
+            "1d8+1d4+2".parse(),
+            Ok(Damage {
+                fixed: 2,
+                ..Damage::dice(1, 8) + Damage::dice(1, 4)
+            })
+        );
+
+        // spacing, count-less dice, flat penalties, odd sizes
+        assert_eq!(
+            " d6 + 2d6 - 1 ".parse(),
+            Ok(Damage {
+                fixed: -1,
+                ..Damage::dice(3, 6)
+            })
+        );
+        assert_eq!("-2".parse::<Damage>().unwrap().fixed, -2);
+        assert_eq!("1d3".parse::<Damage>().unwrap().dmg, 2.0);
+
+        // malformed
+        for bad in [
+            "", "2d", "d", "2x6", "2d6+", "2d6++3", "1d0", "3-1d4", "2d6+1.5",
+        ] {
+            assert!(bad.parse::<Damage>().is_err(), "{bad:?}");
+        }
+        assert_eq!(
+            "2d6+x".parse::<Damage>(),
+            Err(ParseError::Malformed("x".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_dmg_parse_error() {
+        let parse = |s: &str| s.parse::<Damage>();
+
+        assert_eq!(parse("2d"), Err(ParseError::Malformed("2d".to_string())));
+        assert_eq!(parse("2x6"), Err(ParseError::Malformed("2x6".to_string())));
+        assert_eq!(parse("2d6+"), Err(ParseError::Malformed("".to_string())));
+        assert_eq!(
+            parse("3-1d4"),
+            Err(ParseError::Malformed("-1d4".to_string()))
+        );
+        assert_eq!(parse("1d0"), Err(ParseError::UnknownDie(0)));
+        assert_eq!(parse("2d1"), Err(ParseError::UnknownDie(1)));
+        assert_eq!(
+            parse("99999999999d6"),
+            Err(ParseError::OutOfRange("99999999999d6".to_string()))
+        );
+        assert_eq!(
+            parse("1+2147483647"),
+            Err(ParseError::OutOfRange("2147483647".to_string()))
+        );
+        assert_eq!(
+            parse("4294967295d6+1d6"),
+            Err(ParseError::OutOfRange("1d6".to_string()))
+        );
+
+        assert_eq!(ParseError::UnknownDie(0).to_string(), "no such die: d0");
+        assert_eq!(
+            ParseError::Malformed("2x6".to_string()).to_string(),
+            "malformed dice term: \"2x6\""
+        );
+    }
+
+    #[test]
+    fn test_dmg_dice() {
+        assert_eq!(Damage::dice(2, 6).dmg, 7.0);
+        assert_eq!(Damage::dice(1, 12).dmg, d12);
+        assert_eq!(Damage::dice(2, 6).dice, [0, 2, 0, 0, 0, 0]);
+
+        // counts add up by faces
+        let dmg = Damage::dice(2, 6) + Damage::dice(1, 8) + Damage::dice(1, 6);
+        assert_eq!(dmg.dmg, 4.0 * d6 + d8 - d6);
+        assert_eq!(dmg.dice, [0, 3, 1, 0, 0, 0]);
+
+        // other sizes only count toward dmg
+        assert_eq!(Damage::dice(1, 3).dmg, 2.0);
+        assert_eq!(Damage::dice(1, 3).dice, [0; 6]);
+    }
+
+    #[test]
+    fn test_dmg_hit() {
+        let dmg = Damage {
+            dmg: 1.0,
+            fixed: 1,
+            ..Default::default()
+        };
+
+        assert_eq!(dmg.hit(), 2.0);
+    }
+
+    #[test]
+    fn test_dmg_crit() {
+        let dmg = Damage {
+            dmg: 1.0,
+            fixed: 1,
+            ..Default::default()
+        };
+
+        assert_eq!(dmg.crit(), 3.0);
+    }
+
+    #[test]
+    fn test_dmg_add() {
+        assert_eq!(
+            Damage {
+                dmg: 1.0,
+                fixed: 1,
+                ..Default::default()
+            } + Damage {
+                dmg: 1.0,
+                fixed: 1,
+                ..Default::default()
+            },
+            Damage {
+                dmg: 2.0,
+                fixed: 2,
+                ..Default::default()
+            }
+        );
+    }
+
+    // Attack
+
+    #[test]
+    fn test_dmg_typed() {
+        let slashing = Damage {
+            dmg: d8,
+            fixed: 4,
+            ..Default::default()
+        };
+        let fire = Damage {
+            dmg: 2.0 * d6,
+            ..Default::default()
+        }
+        .of(DamageType::Fire);
+
+        // the type doesn't change the damage itself
+        let typed = slashing.of(DamageType::Slashing);
+        assert_eq!(typed.hit(), slashing.hit());
+        assert_eq!(typed.crit(), slashing.crit());
+
+        // mixed types are kept apart
+        let flame_tongue = typed + fire;
+        assert_eq!(flame_tongue.hit(), slashing.hit() + 2.0 * d6);
+        assert_eq!(flame_tongue.crit(), slashing.crit() + 4.0 * d6);
+        assert_eq!(flame_tongue.of_type(DamageType::Slashing), slashing);
+        assert_eq!(flame_tongue.of_type(DamageType::Fire).dmg, 2.0 * d6);
+        assert_eq!(flame_tongue.of_type(DamageType::Cold), Damage::default());
+
+        // untyped damage has no typed part
+        assert_eq!(
+            (slashing + fire).of_type(DamageType::Slashing),
+            Damage::default()
+        );
+        assert_eq!((fire + fire).of_type(DamageType::Fire).dmg, 4.0 * d6);
+    }
+
+    #[test]
+    fn test_resistance() {
+        let slashing = Damage {
+            dmg: d8,
+            fixed: 4,
+            ..Default::default()
+        }
+        .of(DamageType::Slashing);
+        let fire = Damage {
+            dmg: 2.0 * d6,
+            ..Default::default()
+        }
+        .of(DamageType::Fire);
+
+        let atk = Attack {
+            hit: 7,
+            dmg: slashing + fire,
+            ..Default::default()
+        };
+        let fire_resistant = Defenses {
+            resistant: vec![DamageType::Fire],
+            ..Default::default()
+        };
+
+        let f = |x: f64| format!("{:.4}", x);
+
+        assert_eq!(
+            atk.expected_damage_vs(15, &Defenses::default()),
+            atk.expected_damage(15)
+        );
+        assert_eq!(
+            f(atk.expected_damage_vs(15, &fire_resistant)),
+            f(atk.of_type(DamageType::Slashing).expected_damage(15)
+                + 0.5 * atk.of_type(DamageType::Fire).expected_damage(15))
+        );
+
+        // the crit's doubled dice are halved too
+        let crits_only = Attack { hit: -30, ..atk };
+        assert_eq!(
+            f(crits_only.expected_damage_vs(15, &fire_resistant)),
+            f(0.05 * (2.0 * d8 + 4.0 + 0.5 * 4.0 * d6))
+        );
+
+        // untyped damage and repeated types are unaffected
+        let untyped = Attack {
+            hit: 7,
+            dmg: Damage {
+                dmg: d8,
+                fixed: 4,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            untyped.expected_damage_vs(15, &fire_resistant),
+            untyped.expected_damage(15)
+        );
+        let twice = Defenses {
+            resistant: vec![DamageType::Fire, DamageType::Fire],
+            ..Default::default()
+        };
+        assert_eq!(
+            atk.expected_damage_vs(15, &twice),
+            atk.expected_damage_vs(15, &fire_resistant)
+        );
+
+        // riders of the type are halved as well
+        let turn = Turn {
+            action: vec![untyped; 2],
+            once_on_hit: fire,
+            ..Default::default()
+        };
+        let no_rider = Turn {
+            once_on_hit: Damage::default(),
+            ..turn.clone()
+        };
+        assert_eq!(
+            f(turn.expected_damage_vs(15, &fire_resistant) - no_rider.expected_damage(15)),
+            f(0.5 * (turn.expected_damage(15) - no_rider.expected_damage(15)))
+        );
+    }
+
+    #[test]
+    fn test_vulnerability() {
+        let slashing = Damage {
+            dmg: d8,
+            fixed: 4,
+            ..Default::default()
+        }
+        .of(DamageType::Slashing);
+        let fire = Damage {
+            dmg: 2.0 * d6,
+            fixed: 1,
+            ..Default::default()
+        }
+        .of(DamageType::Fire);
+
+        let atk = Attack {
+            hit: 7,
+            dmg: slashing + fire,
+            ..Default::default()
+        };
+        let fire_vulnerable = Defenses {
+            vulnerable: vec![DamageType::Fire],
+            ..Default::default()
+        };
+
+        let f = |x: f64| format!("{:.4}", x);
+
+        assert_eq!(
+            f(atk.expected_damage_vs(15, &fire_vulnerable)),
+            f(atk.expected_damage(15) + atk.of_type(DamageType::Fire).expected_damage(15))
+        );
+
+        // the whole crit is doubled, fixed damage included
+        let crits_only = Attack { hit: -30, ..atk };
+        assert_eq!(
+            f(crits_only.expected_damage_vs(15, &fire_vulnerable)),
+            f(0.05 * (slashing.crit() + 2.0 * fire.crit()))
+        );
+
+        // both at once cancel out
+        let both = Defenses {
+            resistant: vec![DamageType::Fire],
+            vulnerable: vec![DamageType::Fire],
+            ..Default::default()
+        };
+        assert_eq!(
+            f(atk.expected_damage_vs(15, &both)),
+            f(atk.expected_damage(15))
+        );
+
+        // untyped damage is unaffected
+        let untyped = Attack {
+            hit: 7,
+            dmg: Damage {
+                dmg: d8,
+                fixed: 4,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            untyped.expected_damage_vs(15, &fire_vulnerable),
+            untyped.expected_damage(15)
+        );
+    }
+
+    #[test]
+    fn test_immunity() {
+        let mace = Attack {
+            hit: 7,
+            dmg: Damage {
+                dmg: d6,
+                fixed: 4,
+                ..Default::default()
+            }
+            .of(DamageType::Bludgeoning),
+            ..Default::default()
+        };
+        let poison = Damage {
+            dmg: 2.0 * d6,
+            ..Default::default()
+        }
+        .of(DamageType::Poison);
+
+        let turn = Turn {
+            action: vec![mace; 2],
+            once_on_hit: poison,
+            ..Default::default()
+        };
+        let immune = Defenses {
+            immune: vec![DamageType::Bludgeoning],
+            // immunity wins over vulnerability
+            vulnerable: vec![DamageType::Bludgeoning],
+            ..Default::default()
+        };
+
+        assert_eq!(mace.expected_damage_vs(15, &immune), 0.0);
+
+        // the attacks still land the poison rider
+        let rider_only = Turn {
+            action: vec![
+                Attack {
+                    dmg: Damage::default(),
+                    ..mace
+                };
+                2
+            ],
+            ..turn.clone()
+        };
+        assert_eq!(
+            format!("{:.4}", turn.expected_damage_vs(15, &immune)),
+            format!("{:.4}", rider_only.expected_damage(15))
+        );
+        assert!(rider_only.expected_damage(15) > 0.0);
+
+        let poison_immune = Defenses {
+            immune: vec![DamageType::Poison, DamageType::Bludgeoning],
+            ..Default::default()
+        };
+        assert_eq!(
+            format!("{:.4}", turn.expected_damage_vs(15, &poison_immune)),
+            format!("{:.4}", 0.0)
+        );
+    }
+
+    #[test]
+    fn test_immunity_exclusive_riders() {
+        let exclusive = |name: &str, dmg| OncePerTurnRider {
+            name: name.to_string(),
+            dmg,
+            stacking: Stacking::Exclusive("concentration".to_string()),
+            ..Default::default()
+        };
+        let fire = Damage::dice(4, 6).of(DamageType::Fire);
+        let cold = Damage::dice(1, 8).of(DamageType::Cold);
+
+        let turn = Turn {
+            action: vec![Attack {
+                hit: 7,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+        .rider(exclusive("fire", fire))
+        .rider(exclusive("cold", cold));
+        let fire_immune = Defenses {
+            immune: vec![DamageType::Fire],
+            ..Default::default()
+        };
+
+        // fire is better, but against the immune target cold is picked
+        let cold_only = Turn {
+            riders: vec![],
+            once_on_hit: cold,
+            ..turn.clone()
+        };
+        assert_eq!(
+            format!("{:.4}", turn.expected_damage_vs(15, &fire_immune)),
+            format!("{:.4}", cold_only.expected_damage(15))
+        );
+        assert_eq!(
+            format!("{:.4}", turn.expected_damage_vs(15, &Defenses::default())),
+            format!("{:.4}", turn.expected_damage(15))
+        );
+    }
+
+    #[test]
+    fn test_save_attack() {
+        let fireball = SaveAttack {
+            dc: 15,
+            save_bonus_of_target: 3,
+            dmg: Damage {
+                dmg: 8.0 * d6,
+                ..Default::default()
+            }
+            .of(DamageType::Fire),
+            ..Default::default()
+        };
+
+        // fails on 1 to 11
+        assert_eq!(fireball.fail_chance(), 11.0 / 20.0);
+        assert_eq!(fireball.expected_damage(15), 11.0 / 20.0 * 28.0);
+        assert_eq!(fireball.expected_damage(25), fireball.expected_damage(15));
+
+        // clamped at always and never
+        let easy = SaveAttack {
+            save_bonus_of_target: -10,
+            ..fireball
+        };
+        assert_eq!(easy.fail_chance(), 1.0);
+        let hard = SaveAttack {
+            save_bonus_of_target: 20,
+            ..fireball
+        };
+        assert_eq!(hard.fail_chance(), 0.0);
+
+        // alongside attack rolls, without landing any riders
+        let atk = Attack {
+            hit: 7,
+            dmg: Damage {
+                dmg: d8,
+                fixed: 4,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let turn = Turn {
+            action: vec![atk],
+            once_on_hit: Damage {
+                dmg: d6,
+                ..Default::default()
+            },
+            saves: vec![fireball],
+            ..Default::default()
+        };
+        let no_save = Turn {
+            saves: vec![],
+            ..turn.clone()
+        };
+
+        assert_eq!(
+            format!("{:.4}", turn.expected_damage(15)),
+            format!(
+                "{:.4}",
+                no_save.expected_damage(15) + fireball.expected_damage(15)
+            )
+        );
+        assert_eq!(turn.stacked_contributions(15)[1].0, "save 1");
+        assert_eq!((turn.clone() + atk).saves, turn.saves);
+
+        let fire_resistant = Defenses {
+            resistant: vec![DamageType::Fire],
+            ..Default::default()
+        };
+        assert_eq!(
+            fireball.expected_damage_vs(15, &fire_resistant),
+            0.5 * fireball.expected_damage(15)
+        );
+    }
+
+    #[test]
+    fn test_save_attack_half_on_save() {
+        let fireball = SaveAttack {
+            dc: 15,
+            save_bonus_of_target: 3,
+            dmg: Damage {
+                dmg: 8.0 * d6,
+                ..Default::default()
+            },
+            half_on_save: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            fireball.expected_damage(15),
+            (11.0 / 20.0 + 9.0 / 20.0 * 0.5) * 28.0
+        );
+
+        // half on a failure, nothing on a success
+        let rogue = SaveAttack {
+            evasion: true,
+            ..fireball
+        };
+        assert_eq!(rogue.expected_damage(15), 11.0 / 20.0 * 0.5 * 28.0);
+
+        // evasion only matters for half damage saves
+        let no_half = SaveAttack {
+            half_on_save: false,
+            ..fireball
+        };
+        assert_eq!(
+            SaveAttack {
+                evasion: true,
+                ..no_half
+            }
+            .expected_damage(15),
+            no_half.expected_damage(15)
+        );
+    }
+
+    #[test]
+    fn test_great_weapon_fighting() {
+        let f = |x: f64| format!("{:.4}", x);
+
+        assert_eq!(f(great_weapon_fighting_value(6)), f(25.0 / 6.0));
+        assert_eq!(f(great_weapon_fighting_value(12)), f(88.0 / 12.0));
+
+        let greatsword = Damage {
+            dmg: 2.0 * d6,
+            fixed: 4,
+            ..Default::default()
+        }
+        .of(DamageType::Slashing);
+        let gwf = greatsword.great_weapon_fighting(6);
+
+        // only the dice improve
+        assert_eq!(f(gwf.dmg), f(2.0 * 25.0 / 6.0));
+        assert_eq!(gwf.fixed, 4);
+        assert_eq!(f(gwf.of_type(DamageType::Slashing).dmg), f(gwf.dmg));
+        assert_eq!(
+            f(gwf.crit() - greatsword.crit()),
+            f(2.0 * (gwf.dmg - greatsword.dmg))
+        );
+
+        // the same from recorded dice keeps them, so the rolls are known
+        let greatsword = Damage {
+            fixed: 4,
+            ..Damage::dice(2, 6)
+        }
+        .of(DamageType::Slashing);
+        let gwf = greatsword.great_weapon_fighting(6);
+
+        assert_eq!(f(gwf.dmg), f(2.0 * 25.0 / 6.0));
+        assert_eq!(gwf.lowest(1), Some(6.0));
+        assert_eq!(gwf.highest(2), Some(28.0));
+
+        let atk = Attack {
+            hit: 7,
+            dmg: gwf,
+            ..Default::default()
+        };
+        let turn = Turn {
+            action: vec![atk],
+            ..Default::default()
+        };
+        let pmf = turn.distribution(15).unwrap();
+        let mean: f64 = pmf.iter().map(|(d, p)| d * p).sum();
+        assert_eq!(f(mean), f(atk.expected_damage(15)));
+    }
+
+    #[test]
+    fn test_savage_attacker() {
+        let f = |x: f64| format!("{:.4}", x);
+
+        // the higher of two d12s
+        assert_eq!(f(savage_attacker_value(1, 12)), f(1222.0 / 144.0));
+        assert_eq!(f(savage_attacker_value(1, 1)), f(1.0));
+        assert!(savage_attacker_value(2, 6) > 2.0 * d6);
+
+        let greataxe = Damage {
+            dmg: d12,
+            fixed: 4,
+            ..Default::default()
+        };
+        assert_eq!(f(greataxe.savage_attacker(1, 12).dmg), f(1222.0 / 144.0));
+        assert_eq!(greataxe.savage_attacker(1, 12).fixed, 4);
+
+        // once per turn, on the first hit
+        let atk = Attack {
+            hit: 7,
+            dmg: greataxe,
+            ..Default::default()
+        };
+        let turn = Turn {
+            action: vec![atk; 2],
+            ..Default::default()
+        };
+        let savage = turn.clone().savage_attacker(1, 12);
+
+        let miss = 1.0 - (atk.hit_chance(15, Roll::Normal) + 0.05);
+        let first_crit = 0.05 + miss * 0.05;
+        let gain = 1222.0 / 144.0 - d12;
+        assert_eq!(
+            f(savage.expected_damage(15)),
+            f(turn.expected_damage(15) + (1.0 - miss * miss) * gain + first_crit * gain)
+        );
+    }
+
+    #[test]
+    fn test_turn_dice_policy() {
+        let f = |x: f64| format!("{:.4}", x);
+
+        let greatsword = Attack {
+            hit: 7,
+            dmg: Damage {
+                fixed: 4,
+                ..Damage::dice(2, 6)
+            },
+            ..Default::default()
+        };
+        let turn = Turn {
+            action: vec![greatsword; 2],
+            ..Default::default()
+        }
+        .divine_smite(2);
+
+        assert_eq!(
+            turn.expected_damage_with_policy(15, DicePolicy::Standard),
+            turn.expected_damage(15)
+        );
+
+        // every die rerolls its 1s and 2s, the smite's d8s too
+        let gwf = Turn {
+            action: vec![
+                Attack {
+                    dmg: greatsword.dmg.great_weapon_fighting(6),
+                    ..greatsword
+                };
+                2
+            ],
+            ..Default::default()
+        }
+        .rider(OncePerTurnRider {
+            dmg: turn.riders[0].dmg.great_weapon_fighting(8),
+            ..turn.riders[0].clone()
+        });
+        assert_eq!(
+            f(turn.expected_damage_with_policy(15, DicePolicy::GreatWeaponFighting)),
+            f(gwf.expected_damage(15))
+        );
+
+        // the weapon's 2d6 rolled twice on the first hit
+        assert_eq!(
+            f(turn.expected_damage_with_policy(15, DicePolicy::SavageAttacker)),
+            f(turn.clone().savage_attacker(2, 6).expected_damage(15))
+        );
+
+        let standard = turn.expected_damage(15);
+        for policy in [DicePolicy::GreatWeaponFighting, DicePolicy::SavageAttacker] {
+            let reroll = turn.with_policy(policy);
+
+            assert!(reroll.expected_damage(15) > standard);
+            // the dice stay recorded
+            let pmf = reroll.distribution(15).unwrap();
+            let mean: f64 = pmf.iter().map(|(d, p)| d * p).sum();
+            assert_eq!(f(mean), f(reroll.expected_damage(15)));
+        }
+
+        // averages can't be rerolled
+        let averaged = Turn {
+            action: vec![Attack {
+                dmg: Damage {
+                    dmg: 7.0,
+                    ..Default::default()
+                },
+                ..greatsword
+            }],
+            ..Default::default()
+        };
+        for policy in [DicePolicy::GreatWeaponFighting, DicePolicy::SavageAttacker] {
+            assert_eq!(averaged.with_policy(policy), averaged);
+        }
+    }
+
+    #[test]
+    fn test_turn_sneak_attack() {
+        let atk = Attack {
+            hit: 7,
+            dmg: Damage {
+                dmg: d6,
+                fixed: 4,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let turn = Turn {
+            action: vec![atk],
+            bonus_action: vec![atk],
+            ..Default::default()
+        };
+        let sneak = turn.clone().sneak_attack(3);
+
+        assert_eq!(sneak.once_on_hit.dmg, 3.0 * d6);
+
+        // lands once if either attack does, with all 6d6 when the first hit crits
+        let landing = atk.hit_chance(15, Roll::Normal) + 0.05;
+        let miss = (1.0 - landing) * (1.0 - landing);
+        let first_crit = 0.05 + (1.0 - landing) * 0.05;
+        let first_hit_crit = first_crit / (1.0 - miss);
+
+        let sneak_dmg =
+            (1.0 - miss) * ((1.0 - first_hit_crit) * 3.0 * d6 + first_hit_crit * 6.0 * d6);
+        assert_eq!(
+            format!(
+                "{:.4}",
+                sneak.expected_damage(15) - turn.expected_damage(15)
+            ),
+            format!("{:.4}", sneak_dmg)
+        );
+
+        // keeps Favored Foe's d4
+        let foe = turn.foe().sneak_attack(3);
+        assert_eq!(foe.once_on_hit.dmg, d4 + 3.0 * d6);
+        assert_eq!(foe, turn.sneak_attack(3).foe());
+    }
+
+    #[test]
+    fn test_turn_divine_smite() {
+        let longsword = Attack {
+            hit: 7,
+            dmg: Damage {
+                dmg: d8,
+                fixed: 4,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let f = |x: f64| format!("{:.4}", x);
+
+        // a single attack crits on 1 in 20, doubling the 2d8
+        let single = Turn {
+            action: vec![longsword],
+            ..Default::default()
+        };
+        let landing = longsword.hit_chance(15, Roll::Normal) + 0.05;
+        assert_eq!(
+            f(single.clone().divine_smite(2).expected_damage(15) - single.expected_damage(15)),
+            f(landing * 2.0 * d8 + 0.05 * 2.0 * d8)
+        );
+
+        // with two attacks the smite still lands once
+        let multi = Turn {
+            action: vec![longsword; 2],
+            ..Default::default()
+        };
+        let miss = (1.0 - landing) * (1.0 - landing);
+        let first_crit = 0.05 + (1.0 - landing) * 0.05;
+        let smitten = multi.clone().divine_smite(2);
+        assert_eq!(
+            f(smitten.expected_damage(15) - multi.expected_damage(15)),
+            f((1.0 - miss) * 2.0 * d8 + first_crit * 2.0 * d8)
+        );
+        assert_eq!(
+            smitten.of_type(DamageType::Radiant).riders[0].dmg.dmg,
+            2.0 * d8
+        );
+
+        // or only on the best attack landing
+        let dagger = Attack {
+            dmg: Damage {
+                dmg: d4,
+                fixed: 4,
+                ..Default::default()
+            },
+            ..longsword
+        };
+        let mixed = Turn {
+            action: vec![longsword],
+            bonus_action: vec![dagger],
+            ..Default::default()
+        };
+        let best = mixed.clone().divine_smite_best(2, 15);
+        assert_eq!(best.action[0].first_hit_bonus.dmg, 2.0 * d8);
+        assert_eq!(best.bonus_action[0], dagger);
+        assert_eq!(
+            f(best.expected_damage(15) - mixed.expected_damage(15)),
+            f(landing * 2.0 * d8 + 0.05 * 2.0 * d8)
+        );
+        assert_eq!(Turn::default().divine_smite_best(2, 15), Turn::default());
+
+        // any copy of the best attack can land it, so with nothing better to
+        // wait for it's the plain smite
+        let best = multi.clone().divine_smite_best(2, 15);
+        assert!(best
+            .action
+            .iter()
+            .all(|a| a.first_hit_bonus.dmg == 2.0 * d8));
+        assert_eq!(f(best.expected_damage(15)), f(smitten.expected_damage(15)));
+
+        let extra = Turn {
+            action: vec![longsword; 2],
+            bonus_action: vec![dagger; 2],
+            ..Default::default()
+        };
+        let best = extra.clone().divine_smite_best(2, 15);
+        let longsword_miss = (1.0 - landing) * (1.0 - landing);
+        assert_eq!(best.bonus_action, vec![dagger; 2]);
+        assert_eq!(
+            f(best.expected_damage(15) - extra.expected_damage(15)),
+            f((1.0 - longsword_miss) * 2.0 * d8 + first_crit * 2.0 * d8)
+        );
+    }
+
+    #[test]
+    fn test_turn_action_surge() {
+        let atk = Attack {
+            hit: 7,
+            dmg: Damage {
+                dmg: d8,
+                fixed: 4,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let turn = Turn {
+            action: vec![atk; 2],
+            bonus_action: vec![atk],
+            ..Default::default()
+        };
+        let surge = turn.action_surge();
+
+        assert_eq!(surge.action, vec![atk; 4]);
+        assert_eq!(surge.bonus_action, turn.bonus_action);
+        assert_eq!(
+            format!("{:.4}", surge.expected_damage(15)),
+            format!(
+                "{:.4}",
+                turn.expected_damage(15) + 2.0 * atk.expected_damage(15)
+            )
+        );
+
+        // one round of surge, then back to the base turn
+        let mut resources = Resources {
+            pools: vec![Pool {
+                name: "Action Surge".to_string(),
+                uses: 1,
+                turn: surge.clone(),
+            }],
+        };
+        assert_eq!(
+            format!(
+                "{:.4}",
+                encounter_damage(&turn, &mut resources, &mut Greedy, 15, 3)
+            ),
+            format!(
+                "{:.4}",
+                surge.expected_damage(15) + 2.0 * turn.expected_damage(15)
+            )
+        );
+    }
+
+    #[test]
+    fn test_attack_builder() {
+        let crossbow = Attack {
+            hit: 5 + 4 + 2 + 1,
+            dmg: Damage {
+                fixed: 5 + 1,
+                ..Damage::dice(1, 6)
+            },
+            crit: Damage::dice(1, 6),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Attack::builder()
+                .hit(12)
+                .dice(1, 6)
+                .fixed(6)
+                .crit_dice(1, 6)
+                .build(),
+            crossbow
+        );
+        assert_eq!(default_builds()[0].action[0], crossbow);
+
+        // repeated calls accumulate
+        assert_eq!(
+            Attack::builder()
+                .dice(1, 8)
+                .dice(2, 6)
+                .hit(2)
+                .hit(3)
+                .build(),
+            Attack {
+                hit: 5,
+                dmg: Damage::dice(1, 8) + Damage::dice(2, 6),
+                ..Default::default()
+            }
+        );
+        assert_eq!(Attack::builder().build(), Attack::default());
+        assert_eq!(Attack::builder().crit_range(19).build().crit_range, 19);
+    }
+
+    #[test]
+    fn test_attack_add() {
+        let dmg = Damage {
+            dmg: 1.0,
+            fixed: 1,
+            ..Default::default()
+        };
+        let dbl_dmg = Damage {
+            dmg: 2.0,
+            fixed: 2,
+            ..Default::default()
+        };
+
+        let atk = Attack {
+            hit: 1,
+            dmg: dmg,
+            crit: dmg,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            atk + atk,
+            Attack {
+                hit: 2,
+                dmg: dbl_dmg,
+                crit: dbl_dmg,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_attack_add_damage() {
+        let dmg = Damage {
+            dmg: 1.0,
+            fixed: 1,
+            ..Default::default()
+        };
+        let dbl_dmg = Damage {
+            dmg: 2.0,
+            fixed: 2,
+            ..Default::default()
+        };
+
+        let atk = Attack {
+            hit: 1,
+            dmg: dmg,
+            crit: dmg,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            atk + dmg,
+            Attack {
+                hit: 1,
+                dmg: dbl_dmg,
+                crit: dmg,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_attack_sharpshooter() {
+        let dmg = Damage {
+            dmg: 1.0,
+            fixed: 1,
+            ..Default::default()
+        };
+        let atk = Attack {
+            hit: 10,
+            dmg: dmg,
+            crit: dmg,
+            ..Default::default()
+        };
+
+        let sharp = Attack {
+            hit: 5,
+            dmg: Damage {
+                dmg: 1.0,
+                fixed: 11,
+                ..Default::default()
+            },
+            crit: dmg,
+            ..Default::default()
+        };
+
+        assert_eq!(atk.sharpshooter(), sharp);
+    }
+
+    #[test]
+    fn test_attack_power_attack_threshold() {
+        let crossbow = default_builds()[0].action[0];
+
+        // AC 14 and up, plain: (32 - AC)/20 * 9.5 + 1/20 * 20
+        // sharpshooter: (27 - AC)/20 * 19.5 + 1/20 * 30
+        // even at AC 23.25
+        assert_eq!(crossbow.power_attack_threshold(10..=30), Some(23));
+        assert!(crossbow.sharpshooter().expected_damage(23) >= crossbow.expected_damage(23));
+        assert!(crossbow.sharpshooter().expected_damage(24) < crossbow.expected_damage(24));
+
+        // the range bounds the answer
+        assert_eq!(crossbow.power_attack_threshold(10..=20), Some(20));
+        assert_eq!(crossbow.power_attack_threshold(24..=30), None);
+
+        // only crits hit AC 32, so the bigger crit wins
+        assert_eq!(crossbow.power_attack_threshold(10..=35), Some(23));
+        assert!(crossbow.sharpshooter().expected_damage(32) > crossbow.expected_damage(32));
+    }
+
+    #[test]
+    fn test_turn_power_attack_threshold() {
+        let builds = default_builds();
+        let xbow = &builds[0];
+
+        assert_eq!(
+            xbow.sharpshooter(),
+            Turn {
+                name: xbow.name.clone(),
+                ..builds[1].clone()
+            }
+        );
+        assert_eq!(xbow.power_attack_threshold(1..=30), Some(23));
+
+        // missing more often costs the rider too, so it stops paying sooner
+        let rider = Turn {
+            once_on_hit: Damage::dice(4, 6),
+            ..xbow.clone()
+        };
+        let ac = rider.power_attack_threshold(1..=30).unwrap();
+        assert!(ac < 23);
+        assert!(rider.sharpshooter().expected_damage(ac) >= rider.expected_damage(ac));
+        assert!(rider.sharpshooter().expected_damage(ac + 1) < rider.expected_damage(ac + 1));
+    }
+
+    #[test]
+    fn test_attack_min_max_damage() {
+        let atk = Attack {
+            hit: 5,
+            dmg: "2d6+3".parse().unwrap(),
+            ..Default::default()
+        };
+
+        // ones on a hit, sixes on a crit
+        assert_eq!(atk.min_damage(15), Some(5.0));
+        assert_eq!(atk.max_damage(15), Some(27.0));
+        // only a crit lands
+        assert_eq!(atk.min_damage(30), Some(7.0));
+
+        let extras = Attack {
+            crit: Damage::dice(1, 6),
+            first_hit_bonus: Damage::dice(1, 8),
+            degrees: Some(1.0),
+            nat20_rider: Some(Damage::dice(1, 4)),
+            ..atk
+        };
+        assert_eq!(extras.min_damage(15), Some(5.0 + 1.0));
+        // margin 20 + 5 - 15
+        assert_eq!(extras.max_damage(15), Some(27.0 + 12.0 + 16.0 + 10.0 + 4.0));
+
+        let floored = Attack {
+            min_hit_damage: Some(9.0),
+            ..atk
+        };
+        assert_eq!(floored.min_damage(15), Some(9.0));
+        assert_eq!(floored.max_damage(15), Some(27.0));
+
+        // dice not built with Damage::dice are unknown
+        let literal = Attack {
+            dmg: Damage {
+                dmg: 2.0 * d6,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(literal.min_damage(15), None);
+        assert_eq!(literal.max_damage(15), None);
+
+        let turn = Turn {
+            action: vec![atk; 2],
+            once_on_hit: Damage::dice(2, 6),
+            saves: vec![SaveAttack {
+                dmg: Damage::dice(8, 6),
+                half_on_save: true,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert_eq!(turn.min_damage(15), Some(5.0 + 5.0 + 2.0 + 8.0));
+        assert_eq!(turn.max_damage(15), Some(27.0 + 27.0 + 24.0 + 48.0));
+
+        // first hit bonuses once per distinct attack, crit riders only in max
+        let turn = Turn {
+            action: vec![extras; 2],
+            ..turn
+        }
+        .rider(OncePerTurnRider {
+            name: "smite".to_string(),
+            dmg: Damage::dice(2, 8),
+            crit_only: true,
+            ..Default::default()
+        });
+        assert_eq!(turn.min_damage(15), Some(2.0 * 5.0 + 1.0 + 2.0 + 8.0));
+        assert_eq!(
+            turn.max_damage(15),
+            Some(2.0 * (27.0 + 12.0 + 10.0 + 4.0) + 16.0 + 24.0 + 32.0 + 48.0)
+        );
+        assert_eq!(Turn::default().min_damage(15), Some(0.0));
+    }
+
+    #[test]
+    fn test_turn_distribution() {
+        let f = |x: f64| format!("{:.4}", x);
+        let mean = |pmf: &[(f64, f64)]| pmf.iter().map(|(d, p)| d * p).sum::<f64>();
+        let total = |pmf: &[(f64, f64)]| pmf.iter().map(|(_, p)| p).sum::<f64>();
+
+        let atk = Attack {
+            hit: 7,
+            dmg: "1d8+4".parse().unwrap(),
+            ..Default::default()
+        };
+
+        // one attack: a miss, then 5 to 12 on a hit and 6 to 20 on a crit
+        let single = Turn {
+            action: vec![atk],
+            ..Default::default()
+        }
+        .distribution(15)
+        .unwrap();
+        assert_eq!(f(single[0].1), f(0.35));
+        assert_eq!(single[1].0, 5.0);
+        assert_eq!(f(single[1].1), f(0.6 / 8.0));
+        assert_eq!(single.last().unwrap().0, 20.0);
+        assert_eq!(f(total(&single)), f(1.0));
+
+        let turn = Turn {
+            action: vec![
+                atk,
+                Attack {
+                    degrees: Some(0.5),
+                    hit_die: Some(4),
+                    crit_multiplier: 3,
+                    min_hit_damage: Some(9.0),
+                    ..atk
+                },
+            ],
+            bonus_action: vec![Attack {
+                crit_range: 19,
+                first_hit_bonus: Damage::dice(1, 6),
+                nat20_rider: Some(Damage::dice(1, 4)),
+                ..atk
+            }],
+            once_on_hit: Damage::dice(2, 6),
+            triggered: vec![(Trigger::Crit, atk)],
+            saves: vec![SaveAttack {
+                dc: 15,
+                save_bonus_of_target: 2,
+                dmg: Damage::dice(3, 6),
+                half_on_save: true,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+        .rider(OncePerTurnRider {
+            name: "smite".to_string(),
+            dmg: Damage::dice(2, 8),
+            crit_only: true,
+            ..Default::default()
+        });
+
+        for ac in [12, 17, 22] {
+            let pmf = turn.distribution(ac).unwrap();
+
+            assert_eq!(f(total(&pmf)), f(1.0));
+            assert_eq!(f(mean(&pmf)), f(turn.expected_damage(ac)));
+            assert!(pmf.windows(2).all(|w| w[0].0 < w[1].0));
+        }
+
+        // the helpers record their dice too
+        let ranger = default_builds()[0].foe();
+        let mark = default_builds()[2].mark();
+        let builds = [
+            ranger.clone(),
+            mark.first_turn,
+            mark.max_damage,
+            default_builds()[2].clone().sneak_attack(2).divine_smite(1),
+            default_builds()[2]
+                .clone()
+                .smite_on_crit(2)
+                .savage_attacker(1, 10),
+            Turn {
+                action: vec![Attack {
+                    hit: 7,
+                    dmg: Damage::dice(2, 6).savage_attacker(2, 6),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+        ];
+        for build in &builds {
+            let pmf = build.distribution(15).unwrap();
+
+            assert_eq!(f(total(&pmf)), f(1.0));
+            assert_eq!(f(mean(&pmf)), f(build.expected_damage(15)));
+        }
+        assert!(ranger.min_damage(15).is_some());
+        assert!(ranger.max_damage(15).is_some());
+
+        assert_eq!(Turn::default().distribution(15), Some(vec![(0.0, 1.0)]));
+        // but not literal dice
+        let literal = Turn {
+            action: vec![Attack {
+                dmg: Damage {
+                    dmg: d8,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert_eq!(literal.distribution(15), None);
+    }
+
+    #[test]
+    fn test_turn_variance() {
+        // a failed save always lands the 1d8+3, so only the d8 spreads
+        let turn = Turn {
+            saves: vec![SaveAttack {
+                dc: 30,
+                dmg: "1d8+3".parse().unwrap(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        assert_eq!(turn.variance(15), Some((64.0 - 1.0) / 12.0));
+        assert_eq!(turn.std_dev(15), Some(5.25f64.sqrt()));
+
+        // flat damage spreads only by landing, 11 in 20 at AC 15
+        let flat = Attack {
+            hit: 5,
+            dmg: Damage {
+                fixed: 10,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            format!("{:.4}", flat.variance(15).unwrap()),
+            format!("{:.4}", 0.55 * 0.45 * 100.0)
+        );
+
+        // a d6 weapon hits 10 in 20 and crits 1 in 20 for 2d6
+        let weapon = Attack {
+            dmg: Damage::dice(1, 6),
+            ..flat
+        };
+        let square = 0.5 * 91.0 / 6.0 + 0.05 * (35.0 / 6.0 + 49.0);
+        let mean = 0.5 * 3.5 + 0.05 * 7.0;
+        assert_eq!(
+            format!("{:.4}", weapon.variance(15).unwrap()),
+            format!("{:.4}", square - mean * mean)
+        );
+
+        // none when the damage isn't dice
+        let literal = Attack {
+            dmg: Damage {
+                dmg: 4.5,
+                ..Default::default()
+            },
+            ..flat
+        };
+        assert_eq!(literal.variance(15), None);
+    }
+
+    #[test]
+    fn test_turn_probability_at_least() {
+        let f = |x: f64| format!("{:.4}", x);
+
+        // a save that always fails, for 1d4
+        let dagger = Turn {
+            saves: vec![SaveAttack {
+                dc: 30,
+                dmg: Damage::dice(1, 4),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert_eq!(dagger.probability_at_least(15, 2.0), Some(0.75));
+        assert_eq!(dagger.probability_at_least(15, 2.5), Some(0.5));
+        assert_eq!(dagger.probability_at_least(15, 4.0), Some(0.25));
+        assert_eq!(dagger.probability_at_least(15, 4.5), Some(0.0));
+
+        // below, at and above the mean of 5.85
+        let turn = Turn {
+            action: vec![Attack {
+                hit: 5,
+                dmg: "2d6+3".parse().unwrap(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let mean = turn.expected_damage(15);
+        assert_eq!(f(mean), f(5.85));
+
+        assert_eq!(turn.probability_at_least(15, 0.0).map(f), Some(f(1.0)));
+        // any hit, or a crit
+        assert_eq!(turn.probability_at_least(15, 1.0).map(f), Some(f(0.55)));
+        // all but the hits rolling 2 on the 2d6
+        assert_eq!(
+            turn.probability_at_least(15, mean).map(f),
+            Some(f(0.5 * 35.0 / 36.0 + 0.05))
+        );
+        // 9 or more on the hit's 2d6, or on the crit's 4d6
+        assert_eq!(
+            turn.probability_at_least(15, 2.0 * mean).map(f),
+            Some(f(0.5 * 10.0 / 36.0 + 0.05 * (1.0 - 70.0 / 1296.0)))
+        );
+        assert_eq!(turn.probability_at_least(15, 28.0), Some(0.0));
+
+        let literal = Turn {
+            once_on_hit: Damage {
+                dmg: d4,
+                ..Default::default()
+            },
+            ..turn
+        };
+        assert_eq!(literal.probability_at_least(15, 1.0), None);
+    }
+
+    #[test]
+    fn test_turn_expected_wasted_attacks() {
+        let f = |x: f64| format!("{:.4}", x);
+
+        // 55% to land at AC 15
+        let dagger = Attack {
+            hit: 5,
+            dmg: Damage::dice(1, 4),
+            ..Default::default()
+        };
+        let turn = Turn {
+            action: vec![dagger; 2],
+            bonus_action: vec![dagger],
+            ..Default::default()
+        };
+
+        // any landed attack drops it, so later ones are wasted
+        assert_eq!(
+            turn.expected_wasted_attacks(15, 1).map(f),
+            Some(f(0.55 + (1.0 - 0.45 * 0.45)))
+        );
+        // even two crits deal at most 16
+        assert_eq!(turn.expected_wasted_attacks(15, 17), Some(0.0));
+
+        // the first hit's sneak attack drops it on its own
+        let sneaky = turn.clone().sneak_attack(2);
+        assert_eq!(
+            sneaky.expected_wasted_attacks(15, 3).map(f),
+            turn.expected_wasted_attacks(15, 1).map(f)
+        );
+
+        assert_eq!(Turn::default().expected_wasted_attacks(15, 1), Some(0.0));
+        let averaged = Turn {
+            action: vec![
+                Attack {
+                    dmg: Damage {
+                        dmg: d4,
+                        ..Default::default()
+                    },
+                    ..dagger
+                };
+                2
+            ],
+            ..Default::default()
+        };
+        assert_eq!(averaged.expected_wasted_attacks(15, 1), None);
+    }
+
+    #[test]
+    fn test_turn_expected_damage_with_hit_cap() {
+        let f = |x: f64| format!("{:.4}", x);
+
+        let turn = Turn {
+            action: vec![Attack {
+                hit: 5,
+                dmg: "2d6+3".parse().unwrap(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        // a hit keeps min(2d6, 7) + 3, a crit 10 unless the 4d6 roll 6 or less
+        assert_eq!(
+            turn.expected_damage_with_hit_cap(15, 10).map(f),
+            Some(f(0.5 * 325.0 / 36.0 + 0.05 * (10.0 - 21.0 / 1296.0)))
+        );
+        assert_eq!(
+            turn.expected_damage_with_hit_cap(15, 27).map(f),
+            Some(f(turn.expected_damage(15)))
+        );
+        assert_eq!(turn.expected_damage_with_hit_cap(15, 0), Some(0.0));
+
+        // each hit is capped apart, so two attacks take twice as much
+        let twice = Turn {
+            action: vec![turn.action[0]; 2],
+            ..Default::default()
+        };
+        assert_eq!(
+            twice.expected_damage_with_hit_cap(15, 10).map(f),
+            turn.expected_damage_with_hit_cap(15, 10)
+                .map(|d| f(2.0 * d))
+        );
+
+        // the riders are capped as a hit of their own
+        let sneaky = Turn {
+            action: vec![Attack {
+                dmg: Damage::dice(1, 4),
+                ..turn.action[0]
+            }],
+            ..Default::default()
+        }
+        .sneak_attack(4);
+        assert!(sneaky.expected_damage_with_hit_cap(15, 10).unwrap() < sneaky.expected_damage(15));
+        assert_eq!(
+            sneaky.expected_damage_with_hit_cap(15, 48).map(f),
+            Some(f(sneaky.expected_damage(15)))
+        );
+
+        let literal = Turn {
+            once_on_hit: Damage {
+                dmg: d4,
+                ..Default::default()
+            },
+            ..turn
+        };
+        assert_eq!(literal.expected_damage_with_hit_cap(15, 10), None);
+    }
+
+    #[test]
+    fn test_turn_percentile() {
+        // a save that always fails, for 1d4
+        let dagger = Turn {
+            saves: vec![SaveAttack {
+                dc: 30,
+                dmg: Damage::dice(1, 4),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert_eq!(dagger.percentile(15, 50.0), Some(2.0));
+        assert_eq!(dagger.percentile(15, 50.1), Some(3.0));
+        assert_eq!(dagger.percentile(15, 0.0), Some(1.0));
+        assert_eq!(dagger.percentile(15, 100.0), Some(4.0));
+
+        // half the rounds miss outright
+        let turn = Turn {
+            action: vec![Attack {
+                hit: 5,
+                dmg: "2d6+3".parse().unwrap(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert_eq!(turn.percentile(15, 40.0), Some(0.0));
+
+        let ps: Vec<_> = (0..=100)
+            .step_by(5)
+            .map(|p| turn.percentile(15, p as f64).unwrap())
+            .collect();
+        assert!(ps.windows(2).all(|w| w[0] <= w[1]));
+        assert!(ps[18] > ps[2]);
+
+        let literal = Turn {
+            once_on_hit: Damage {
+                dmg: d4,
+                ..Default::default()
+            },
+            ..turn
+        };
+        assert_eq!(literal.percentile(15, 50.0), None);
+    }
+
+    #[test]
+    fn test_attack_great_weapon_master() {
+        let dmg = Damage {
+            dmg: 1.0,
+            fixed: 1,
+            ..Default::default()
+        };
+        let atk = Attack {
+            hit: 10,
+            dmg,
+            crit: dmg,
+            ..Default::default()
+        };
+
+        let gwm = Attack {
+            hit: 5,
+            dmg: Damage {
+                dmg: 1.0,
+                fixed: 11,
+                ..Default::default()
+            },
+            crit: dmg,
+            ..Default::default()
+        };
+
+        assert_eq!(atk.great_weapon_master(), gwm);
+        assert_eq!(atk.power_attack(3, 6).hit, 7);
+        assert_eq!(atk.power_attack(3, 6).dmg.fixed, 7);
+    }
+
+    #[test]
+    fn test_attack_fixed_dmg() {
+        let atk = Attack {
+            dmg: Damage {
+                dmg: 0.0,
+                fixed: 20,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(atk.expected_damage(11), 10.0);
+        assert_eq!(atk.expected_damage(20), 1.0);
+        assert_eq!(atk.expected_damage(21), 1.0);
+
+        assert_eq!(atk.expected_damage(2), 19.0);
+    }
+
+    #[test]
+    //#[ignore]
+    fn test_attack_fumble() {
+        let atk = Attack {
+            dmg: Damage {
+                dmg: 0.0,
+                fixed: 20,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(atk.expected_damage(1), 19.0);
+    }
+
+    #[test]
+    fn test_attack_hit_bonus() {
+        let atk = Attack {
+            hit: 5,
+            dmg: Damage {
+                dmg: 0.0,
+                fixed: 20,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(atk.expected_damage(11), 15.0);
+        assert_eq!(atk.expected_damage(21), 5.0);
+    }
+
+    #[test]
+    fn test_attack_dmg_crit() {
+        let atk = Attack {
+            dmg: Damage {
+                dmg: 20.0,
+                fixed: 0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(atk.expected_damage(11), 11.0);
+
+        let atk = Attack {
+            dmg: Damage {
+                dmg: 20.0,
+                fixed: 20,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(atk.expected_damage(11), 21.0);
+    }
+
+    #[test]
+    fn test_attack_crit_only_dmg() {
+        let atk = Attack {
+            crit: Damage {
+                dmg: 10.0,
+                fixed: 0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(atk.expected_damage(11), 1.0);
+
+        let atk = Attack {
+            crit: Damage {
+                dmg: 0.0,
+                fixed: 20,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(atk.expected_damage(11), 1.0);
+    }
+
+    #[test]
+    fn test_attack_advantage() {
+        let atk = Attack {
+            hit: 5,
+            dmg: Damage {
+                dmg: d8,
+                fixed: 3,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let f = |x: f64| format!("{:.4}", x);
+
+        // 10 faces hit, 1 crits
+        assert_eq!(atk.hit_chance(15, Roll::Normal), 0.5);
+        assert_eq!(
+            f(atk.hit_chance(15, Roll::Advantage)),
+            f(1.0 - 0.45 * 0.45 - 0.0975)
+        );
+        assert_eq!(f(atk.crit_chance(Roll::Advantage)), f(1.0 - 0.95 * 0.95));
+        assert_eq!(
+            f(atk.hit_chance(15, Roll::Disadvantage)),
+            f(0.55 * 0.55 - 0.0025)
+        );
+        assert_eq!(f(atk.crit_chance(Roll::Disadvantage)), f(0.05 * 0.05));
+
+        assert_eq!(
+            atk.expected_damage_with(15, Roll::Normal),
+            atk.expected_damage(15)
+        );
+        assert_eq!(
+            f(atk.expected_damage_with(15, Roll::Advantage)),
+            f(0.7 * 7.5 + 0.0975 * 12.0)
+        );
+        assert_eq!(
+            f(atk.expected_damage_with(15, Roll::Disadvantage)),
+            f(0.3 * 7.5 + 0.0025 * 12.0)
+        );
+
+        // a natural 1 still misses and a natural 20 still crits
+        let sure = Attack { hit: 30, ..atk };
+        assert_eq!(
+            f(sure.hit_chance(5, Roll::Advantage) + sure.crit_chance(Roll::Advantage)),
+            f(1.0 - 0.05 * 0.05)
+        );
+        assert_eq!(
+            f(sure.hit_chance(5, Roll::Disadvantage) + sure.crit_chance(Roll::Disadvantage)),
+            f(0.95 * 0.95)
+        );
+
+        let hopeless = Attack { hit: -10, ..atk };
+        assert_eq!(hopeless.hit_chance(30, Roll::Advantage), 0.0);
+        assert_eq!(
+            f(hopeless.expected_damage_with(30, Roll::Advantage)),
+            f(0.0975 * 12.0)
+        );
+
+        // each face weighted by the chance it's the kept die
+        let degrees = Attack {
+            degrees: Some(1.0),
+            ..Default::default()
+        };
+        assert_eq!(f(degrees.expected_damage_with(10, Roll::Normal)), f(2.75));
+        assert_eq!(
+            f(degrees.expected_damage_with(10, Roll::Advantage)),
+            f(4.5375)
+        );
+        assert_eq!(
+            f(degrees.expected_damage_with(10, Roll::Disadvantage)),
+            f(0.9625)
+        );
+
+        // riders land if any attack does
+        let turn = Turn {
+            action: vec![atk; 2],
+            once_on_hit: Damage {
+                dmg: d6,
+                fixed: 0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let miss = 0.45 * 0.45 * 0.45 * 0.45;
+        let first_crit = 0.0975 + (1.0 - 0.7975) * 0.0975;
+        assert_eq!(
+            f(turn.expected_damage_with(15, Roll::Advantage)),
+            f(2.0 * atk.expected_damage_with(15, Roll::Advantage)
+                + (1.0 - miss) * d6
+                + first_crit * d6)
+        );
+    }
+
+    #[test]
+    fn test_attack_elven_accuracy() {
+        let atk = Attack {
+            hit: 5,
+            dmg: Damage {
+                dmg: d8,
+                fixed: 3,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let f = |x: f64| format!("{:.4}", x);
+
+        // about 14.3% crits
+        assert_eq!(f(atk.crit_chance(Roll::ElvenAccuracy)), f(0.142625));
+        assert_eq!(
+            f(atk.hit_chance(15, Roll::ElvenAccuracy) + atk.crit_chance(Roll::ElvenAccuracy)),
+            f(1.0 - 0.45 * 0.45 * 0.45)
+        );
+        assert_eq!(
+            f(atk.expected_damage_with(15, Roll::ElvenAccuracy)),
+            f((1.0 - 0.45 * 0.45 * 0.45 - 0.142625) * 7.5 + 0.142625 * 12.0)
+        );
+
+        // the third die over plain advantage's two
+        assert_eq!(f(atk.crit_chance(Roll::Advantage)), f(0.0975));
+        assert_eq!(f(atk.hit_chance(15, Roll::Advantage)), f(0.7));
+        assert_eq!(f(atk.hit_chance(15, Roll::ElvenAccuracy)), f(0.76625));
+        for ac in 10..=25 {
+            let lands = |roll: Roll| atk.hit_chance(ac, roll) + atk.crit_chance(roll);
+
+            assert!(lands(Roll::ElvenAccuracy) > lands(Roll::Advantage));
+            assert!(atk.crit_chance(Roll::ElvenAccuracy) > atk.crit_chance(Roll::Advantage));
+        }
+
+        // crit fishing pays off more than with plain advantage
+        let crit_fisher = Attack {
+            crit: Damage {
+                dmg: 4.0 * d8,
+                fixed: 0,
+                ..Default::default()
+            },
+            ..atk
+        };
+        for ac in 10..=25 {
+            assert!(
+                crit_fisher.expected_damage_with(ac, Roll::ElvenAccuracy)
+                    > crit_fisher.expected_damage_with(ac, Roll::Advantage)
+            );
+        }
+    }
+
+    #[test]
+    fn test_attack_lucky() {
+        let atk = Attack {
+            hit: 5,
+            dmg: Damage {
+                dmg: d8,
+                fixed: 3,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let lucky = Attack { lucky: true, ..atk };
+
+        let f = |x: f64| format!("{:.4}", x);
+        let lands = |ac, roll| lucky.hit_chance(ac, roll) + lucky.crit_chance(roll);
+
+        assert_eq!(f(lucky.crit_chance(Roll::Normal)), f(0.0525));
+        // a 1 rerolled into another 1 still misses
+        assert_eq!(f(lands(5, Roll::Normal)), f(1.0 - 1.0 / 400.0));
+
+        // against high AC every landing face is a twentieth more likely
+        for ac in 20..=25 {
+            assert_eq!(
+                f(lucky.expected_damage(ac) / atk.expected_damage(ac)),
+                f(1.05)
+            );
+        }
+        assert_eq!(f(lands(25, Roll::Normal)), f(21.0 / 400.0));
+
+        for ac in 5..=25 {
+            assert!(lucky.expected_damage(ac) > atk.expected_damage(ac));
+            assert!(lucky.expected_damage(ac) < atk.expected_damage_with(ac, Roll::Advantage));
+        }
+
+        // on a whole turn, and the distribution agrees
+        let turn = Turn {
+            action: vec![atk; 2],
+            ..Default::default()
+        };
+        assert_eq!(turn.clone().lucky().action, vec![lucky; 2]);
+        let dist = Turn {
+            action: vec![Attack {
+                dmg: "1d8+3".parse().unwrap(),
+                ..lucky
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            f(dist
+                .distribution(21)
+                .unwrap()
+                .iter()
+                .map(|(d, p)| d * p)
+                .sum()),
+            f(dist.expected_damage(21))
+        );
+    }
+
+    #[test]
+    fn test_attack_lucky_advantage() {
+        let atk = Attack {
+            hit: 5,
+            dmg: Damage {
+                fixed: 10,
+                ..Default::default()
+            },
+            lucky: true,
+            ..Default::default()
+        };
+
+        let f = |x: f64| format!("{:.4}", x);
+        let lands = |ac, roll| atk.hit_chance(ac, roll) + atk.crit_chance(roll);
+
+        // each die lands at AC 15 on 11 faces, or on a 1 that rerolls into them
+        let one = 11.0 * 21.0 / 400.0;
+        assert_eq!(
+            f(lands(15, Roll::Advantage)),
+            f(1.0 - (1.0 - one) * (1.0 - one))
+        );
+        assert_eq!(
+            f(lands(15, Roll::ElvenAccuracy)),
+            f(1.0 - (1.0 - one).powi(3))
+        );
+        assert_eq!(f(lands(15, Roll::Disadvantage)), f(one * one));
+
+        // luck helps on top of advantage
+        let unlucky = Attack {
+            lucky: false,
+            ..atk
+        };
+        for roll in [Roll::Advantage, Roll::ElvenAccuracy, Roll::Disadvantage] {
+            for ac in 5..=25 {
+                assert!(
+                    atk.expected_damage_with(ac, roll) > unlucky.expected_damage_with(ac, roll)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_attack_crit_range() {
+        let atk = Attack {
+            hit: 5,
+            dmg: Damage {
+                dmg: d8,
+                fixed: 3,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let champion = Attack {
+            crit_range: 19,
+            ..atk
+        };
+
+        // twice as many crits, taken out of the hits
+        assert_eq!(champion.crit_chance(Roll::Normal), 0.1);
+        assert_eq!(champion.hit_chance(15, Roll::Normal), 0.45);
+        assert_eq!(
+            format!("{:.4}", champion.expected_damage(15)),
+            format!("{:.4}", 0.45 * 7.5 + 0.1 * 12.0)
+        );
+        assert_eq!(champion.expected_damage_rational(15), Some((183, 40)));
+
+        // a 19 crits even when it wouldn't otherwise hit
+        assert_eq!(champion.hit_chance(30, Roll::Normal), 0.0);
+        assert_eq!(
+            format!("{:.4}", champion.expected_damage(30)),
+            format!("{:.4}", 0.1 * 12.0)
+        );
+        assert_eq!(
+            format!("{:.4}", champion.fumble_cost(5)),
+            format!("{:.4}", atk.fumble_cost(5))
+        );
+
+        // the wider range applies
+        assert_eq!((atk + champion).crit_range, 19);
+        assert_eq!(atk.sharpshooter().crit_range, 20);
+
+        // a natural 20 rider ignores the crit range
+        let rider = Damage {
+            dmg: d10,
+            fixed: 0,
+            ..Default::default()
+        };
+        for a in [atk, champion] {
+            let with_rider = Attack {
+                nat20_rider: Some(rider),
+                ..a
+            };
+            assert_eq!(
+                format!(
+                    "{:.4}",
+                    with_rider.expected_damage(15) - a.expected_damage(15)
+                ),
+                format!("{:.4}", d10 / 20.0)
+            );
+        }
+
+        // riders and crit triggers see the wider range too
+        let turn = Turn {
+            action: vec![champion; 2],
+            triggered: vec![(Trigger::Crit, atk)],
+            ..Default::default()
+        };
+        assert_eq!(
+            format!("{:.4}", turn.expected_damage(15)),
+            format!(
+                "{:.4}",
+                2.0 * champion.expected_damage(15) + (1.0 - 0.9 * 0.9) * atk.expected_damage(15)
+            )
+        );
+    }
+
+    #[test]
+    fn test_turn_bless() {
+        let atk = Attack {
+            hit: 5,
+            dmg: Damage {
+                dmg: d8,
+                fixed: 3,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let turn = Turn {
+            action: vec![atk; 2],
+            ..Default::default()
+        };
+        let blessed = turn.bless();
+
+        assert!(blessed.action.iter().all(|a| a.hit_die == Some(4)));
+
+        // a d4 of 1 to 4 against AC 18 hits on 8 to 11 of the 18 non-crit faces
+        assert_eq!(
+            format!("{:.4}", blessed.action[0].hit_chance(18, Roll::Normal)),
+            format!("{:.4}", 9.5 / 20.0)
+        );
+        assert_eq!(
+            format!("{:.4}", blessed.expected_damage(18)),
+            format!("{:.4}", 2.0 * (9.5 / 20.0 * 7.5 + 0.05 * 12.0))
+        );
+        assert_eq!(
+            blessed.action[0].expected_damage_rational(18),
+            Some((333, 80))
+        );
+
+        // only a natural 1 misses, so Bless can't help where a flat +2.5 would
+        let sure = Attack { hit: 16, ..atk };
+        assert_eq!(
+            Attack {
+                hit_die: Some(4),
+                ..sure
+            }
+            .hit_chance(18, Roll::Normal),
+            sure.hit_chance(18, Roll::Normal)
+        );
+
+        // doesn't stack with itself
+        assert_eq!(blessed.bless(), blessed);
+    }
+
+    #[test]
+    fn test_attack_dueling() {
+        let atk = Attack {
+            hit: 7,
+            dmg: Damage {
+                dmg: d8,
+                fixed: 4,
+                ..Default::default()
+            },
+            crit: Damage {
+                dmg: d6,
+                fixed: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let dueling = atk.dueling();
+
+        assert_eq!(dueling.dmg.fixed, 6);
+        assert_eq!(
+            Attack {
+                dmg: Damage {
+                    fixed: 4,
+                    ..dueling.dmg
+                },
+                ..dueling
+            },
+            atk
+        );
+
+        // the +2 isn't doubled by a crit
+        assert_eq!(dueling.on_crit() - atk.on_crit(), 2.0);
+        assert_eq!(
+            format!(
+                "{:.4}",
+                dueling.expected_damage(15) - atk.expected_damage(15)
+            ),
+            format!("{:.4}", 0.65 * 2.0)
+        );
+    }
+
+    #[test]
+    fn test_attack_brutal_critical() {
+        let atk = Attack {
+            hit: 7,
+            dmg: Damage {
+                dmg: d12,
+                fixed: 4,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let brutal = atk.brutal_critical(1, 12);
+
+        assert_eq!(brutal.crit_extra, Damage::dice(1, 12));
+        assert_eq!(brutal.crit_extra.dmg, d12);
+        assert_eq!(brutal.crit, atk.crit);
+
+        // one more die on a crit, not two, and nothing on a hit
+        assert_eq!(
+            format!(
+                "{:.4}",
+                brutal.expected_damage(15) - atk.expected_damage(15)
+            ),
+            format!("{:.4}", 0.05 * d12)
+        );
+        assert_eq!(brutal.on_hit(), atk.on_hit());
+        assert_eq!(brutal.sensitivity(15), atk.sensitivity(15));
+        // 12/20 * 10.5 + 1/20 * (10.5 + 6.5 + 6.5)
+        assert_eq!(brutal.expected_damage_rational(15), Some((299, 40)));
+
+        // the crit itself doubles its dice
+        let doubled = Attack {
+            crit: Damage {
+                dmg: d12,
+                ..Default::default()
+            },
+            ..atk
+        };
+        assert_eq!(
+            format!(
+                "{:.4}",
+                doubled.expected_damage(15) - atk.expected_damage(15)
+            ),
+            format!("{:.4}", 0.05 * 2.0 * d12)
+        );
+    }
+
+    #[test]
+    fn test_attack_crit_die_override() {
+        let f = |x: f64| format!("{:.4}", x);
+
+        let weapon = Attack {
+            hit: 7,
+            dmg: Damage {
+                fixed: 4,
+                ..Damage::dice(1, 8)
+            }
+            .of(DamageType::Piercing),
+            ..Default::default()
+        };
+        let brutal = weapon.crit_die_override(12);
+
+        // 2d12 + 4 on a crit rather than 2d8 + 4, a hit is unchanged
+        assert_eq!(brutal.on_hit(), weapon.on_hit());
+        assert_eq!(brutal.on_crit(), 17.0);
+        assert_eq!(weapon.on_crit(), 13.0);
+        assert_eq!(
+            f(brutal.expected_damage(15) - weapon.expected_damage(15)),
+            f(0.05 * 4.0)
+        );
+        assert_eq!(brutal.max_damage(15), Some(28.0));
+        assert_eq!(brutal.min_damage(30), Some(6.0));
+
+        // not the same as a d12 more only on crits
+        let extra = Attack {
+            crit_extra: Damage::dice(1, 12),
+            ..weapon
+        };
+        assert!(extra.expected_damage(15) > brutal.expected_damage(15));
+
+        // the d12s stay piercing, and what's added later is rolled too
+        let resistant = Defenses {
+            resistant: vec![DamageType::Piercing],
+            ..Default::default()
+        };
+        assert_eq!(
+            f(brutal.expected_damage_vs(15, &resistant)),
+            f(brutal.expected_damage(15) / 2.0)
+        );
+        let power = brutal.great_weapon_master();
+        assert_eq!(power.on_crit(), 27.0);
+
+        let turn = Turn {
+            action: vec![brutal; 2],
+            ..Default::default()
+        };
+        let pmf = turn.distribution(15).unwrap();
+        let mean: f64 = pmf.iter().map(|(d, p)| d * p).sum();
+        assert_eq!(f(mean), f(turn.expected_damage(15)));
+
+        // averages don't say which dice to swap
+        let averaged = Attack {
+            dmg: Damage {
+                dmg: d8,
+                ..Default::default()
+            },
+            ..weapon
+        };
+        assert_eq!(averaged.crit_die_override(12), averaged);
+    }
+
+    #[test]
+    fn test_attack_crit_multiplier() {
+        let f = |x: f64| format!("{:.4}", x);
+
+        let dmg = Damage {
+            fixed: 3,
+            ..Damage::dice(2, 6)
+        };
+        assert_eq!(dmg.crit_with(2), dmg.crit());
+        assert_eq!(dmg.crit_with(3), 3.0 + 21.0);
+
+        let atk = Attack {
+            hit: 5,
+            dmg,
+            ..Default::default()
+        };
+        let triple = Attack {
+            crit_multiplier: 3,
+            ..atk
+        };
+
+        // 10/20 * 10 + 1/20 * 17, then a third roll of the dice on the crit
+        assert_eq!(f(atk.expected_damage(15)), f(5.85));
+        assert_eq!(f(triple.expected_damage(15)), f(6.2));
+        assert_eq!(
+            f(triple.expected_damage(15) - atk.expected_damage(15)),
+            f(0.05 * 7.0)
+        );
+        assert_eq!((atk + triple).crit_multiplier, 3);
+
+        // crit dice and first hit bonuses are multiplied too
+        let deft = Attack {
+            crit: Damage::dice(1, 6),
+            first_hit_bonus: Damage::dice(1, 8),
+            ..triple
+        };
+        assert_eq!(
+            f(deft.expected_damage(15) - triple.expected_damage(15)),
+            f(0.05 * 3.0 * d6 + 0.5 * d8 + 0.05 * 3.0 * d8)
+        );
+
+        // riders of a turn roll as many times as the attack that landed them
+        let turn = Turn {
+            action: vec![triple],
+            once_on_hit: Damage::dice(1, 6),
+            ..Default::default()
+        };
+        assert_eq!(
+            f(turn.expected_damage(15) - triple.expected_damage(15)),
+            f(0.55 * d6 + 0.05 * 2.0 * d6)
+        );
+        assert_eq!(
+            f(turn.clone().smite_on_crit(2).expected_damage(15) - turn.expected_damage(15)),
+            f(0.05 * 3.0 * 2.0 * d8)
+        );
+    }
+
+    #[test]
+    fn test_dmg_dpr_calc() {
+        let crossbow = crate::Attack {
+            hit: 12,
+            dmg: Damage {
+                dmg: 1.0 * d6,
+                fixed: 6,
+                ..Default::default()
+            },
+            crit: Damage {
+                dmg: 1.0 * d6,
+                fixed: 0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        // https://rpgbot.net/dnd5/tools/dpr-calculator/
+        assert_eq!(crossbow.expected_damage(17), 8.125);
+
+        let rando = crate::Attack {
+            hit: 8,
+            dmg: Damage {
+                dmg: 2.0 * d6,
+                fixed: 5,
+                ..Default::default()
+            },
+            crit: Damage {
+                dmg: 1.0 * d4,
+                fixed: 3,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(format!("{:.4}", rando.expected_damage(16)), "8.5500")
+    }
+
+    // Turn
+
+    #[test]
+    fn test_turn_actions() {
+        let atk = Attack {
+            dmg: Damage {
+                dmg: 20.0,
+                fixed: 20,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let turn = Turn {
+            action: vec![atk],
+            ..Default::default()
+        };
+
+        assert_eq!(turn.expected_damage(11), atk.expected_damage(11));
+
+        let turn = Turn {
+            action: vec![atk; 2],
+            ..Default::default()
+        };
+
+        assert_eq!(turn.expected_damage(11), 2.0 * atk.expected_damage(11));
+    }
+
+    #[test]
+    fn test_turn_bonus_actions() {
+        let atk = Attack {
+            dmg: Damage {
+                dmg: 20.0,
+                fixed: 20,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let turn = Turn {
+            bonus_action: vec![atk],
+            ..Default::default()
+        };
+
+        assert_eq!(turn.expected_damage(11), atk.expected_damage(11));
+
+        let turn = Turn {
+            bonus_action: vec![atk; 2],
+            ..Default::default()
+        };
+
+        assert_eq!(turn.expected_damage(11), 2.0 * atk.expected_damage(11));
+    }
+
+    #[test]
+    fn test_turn_actions_and_bonus_actions() {
+        let atk = Attack {
+            dmg: Damage {
+                dmg: 20.0,
+                fixed: 20,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let turn = Turn {
+            action: vec![atk; 2],
+            bonus_action: vec![atk; 2],
+            ..Default::default()
+        };
+
+        assert_eq!(turn.expected_damage(11), 4.0 * atk.expected_damage(11));
+    }
+
+    #[test]
+    fn test_turn_two_weapon_fighting() {
+        let shortsword = Attack {
+            hit: 5,
+            dmg: Damage {
+                dmg: d6,
+                fixed: 3,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let offhand = Attack {
+            dmg: Damage {
+                dmg: d6,
+                ..Default::default()
+            },
+            ..shortsword
+        };
+
+        let turn = Turn {
+            action: vec![shortsword],
+            bonus_action: vec![offhand],
+            ..Default::default()
+        };
+        let twf = turn.clone().two_weapon_fighting(3);
+
+        assert_eq!(twf.action, turn.action);
+        assert_eq!(twf.bonus_action, vec![shortsword]);
+        assert_eq!(
+            twf.expected_damage(15),
+            2.0 * shortsword.expected_damage(15)
+        );
+    }
+
+    #[test]
+    fn test_turn_polearm_master() {
+        let glaive = Attack::builder().hit(7).dice(1, 10).fixed(4).build();
+        let turn = Turn {
+            action: vec![glaive; 2],
+            ..Default::default()
+        };
+        let pam = turn.clone().polearm_master(7);
+
+        assert_eq!(pam.action, turn.action);
+        assert_eq!(pam.bonus_action.len(), 1);
+        assert_eq!(pam.bonus_action[0].hit, 7);
+        assert_eq!(pam.bonus_action[0].dmg, Damage::dice(1, 4));
+        assert_eq!(
+            pam.expected_damage(15),
+            turn.expected_damage(15) + pam.bonus_action[0].expected_damage(15)
+        );
+    }
+
+    #[test]
+    fn test_turn_crossbow_expert() {
+        let xbow = default_builds()[0].action[0];
+        let sharp = xbow.sharpshooter();
+
+        let turn = Turn {
+            action: vec![sharp; 2],
+            ..Default::default()
+        }
+        .crossbow_expert(sharp);
+
+        assert_eq!(turn.bonus_action, vec![sharp]);
+        for a in turn.action.iter().chain(&turn.bonus_action) {
+            assert_eq!(a.hit, xbow.hit - 5);
+            assert_eq!(a.dmg.fixed, xbow.dmg.fixed + 10);
+        }
+        assert_eq!(
+            Turn {
+                name: "sharp xbow".to_string(),
+                ..turn
+            },
+            default_builds()[1]
+        );
+    }
+
+    #[test]
+    fn test_turn_reactions() {
+        let atk = Attack {
+            dmg: Damage {
+                dmg: 20.0,
+                fixed: 20,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let turn = Turn {
+            reaction: vec![atk],
+            ..Default::default()
+        };
+
+        assert_eq!(turn.expected_damage(11), atk.expected_damage(11));
+
+        let turn = Turn {
+            reaction: vec![atk; 2],
+            ..Default::default()
+        };
+
+        assert_eq!(turn.expected_damage(11), 2.0 * atk.expected_damage(11));
+
+        // an opportunity attack that comes up one round in four
+        let turn = Turn {
+            action: vec![atk],
+            reaction: vec![atk],
+            reaction_chance: Some(0.25),
+            ..Default::default()
+        };
+
+        assert_eq!(turn.expected_damage(11), 1.25 * atk.expected_damage(11));
+        assert_eq!(
+            (turn.clone() + atk).expected_damage(11),
+            1.25 * (atk + atk).expected_damage(11)
+        );
+
+        // sneak attack is once per turn, and the reaction is on another turn
+        let sneaky = Turn {
+            action: vec![atk],
+            reaction: vec![atk],
+            ..Default::default()
+        }
+        .sneak_attack(1);
+        let alone = Turn {
+            action: vec![atk],
+            ..Default::default()
+        }
+        .sneak_attack(1);
+        assert_eq!(
+            format!("{:.4}", sneaky.expected_damage(11)),
+            format!("{:.4}", 2.0 * alone.expected_damage(11))
+        );
+
+        let dice = Turn {
+            action: vec![Attack {
+                dmg: "1d8+3".parse().unwrap(),
+                ..atk
+            }],
+            reaction: vec![Attack {
+                dmg: "1d10+3".parse().unwrap(),
+                ..atk
+            }],
+            reaction_chance: Some(0.5),
+            ..Default::default()
+        };
+        let pmf = dice.distribution(15).unwrap();
+        assert_eq!(
+            format!("{:.4}", pmf.iter().map(|(d, p)| d * p).sum::<f64>()),
+            format!("{:.4}", dice.expected_damage(15))
+        );
+        assert_eq!(dice.max_damage(15), Some(19.0 + 23.0));
+        assert_eq!(dice.min_damage(15), Some(4.0));
+    }
+
+    #[test]
+    fn test_turn_add_damage() {
+        let dmg = Damage {
+            dmg: 1.0,
+            fixed: 1,
+            ..Default::default()
+        };
+        let dbl_dmg = Damage {
+            dmg: 2.0,
+            fixed: 2,
+            ..Default::default()
+        };
+
+        let atk = Attack {
+            hit: 1,
+            dmg: dmg,
+            crit: dmg,
+            ..Default::default()
+        };
+
+        let doublish_atk = Attack {
+            hit: 1,
+            dmg: dbl_dmg,
+            crit: dmg,
+            ..Default::default()
+        };
+
+        let turn = Turn {
+            action: vec![atk; 2],
+            bonus_action: vec![atk; 3],
+            once_on_hit: dmg,
+            name: "myturn".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            turn + dmg,
+            Turn {
+                action: vec![doublish_atk; 2],
+                bonus_action: vec![doublish_atk; 3],
+                once_on_hit: dmg,
+                name: "myturn".to_string(),
+                ..Default::default()
+            }
+        )
+    }
+
+    #[test]
+    fn test_turn_add_attack() {
+        let dmg = Damage {
+            dmg: 1.0,
+            fixed: 1,
+            ..Default::default()
+        };
+        let dbl_dmg = Damage {
+            dmg: 2.0,
+            fixed: 2,
+            ..Default::default()
+        };
+
+        let atk = Attack {
+            hit: 1,
+            dmg: dmg,
+            crit: dmg,
+            ..Default::default()
+        };
+
+        let dbl_atk = Attack {
+            hit: 2,
+            dmg: dbl_dmg,
+            crit: dbl_dmg,
+            ..Default::default()
+        };
+
+        let turn = Turn {
+            action: vec![atk; 2],
+            bonus_action: vec![atk; 3],
+            once_on_hit: dmg,
+            name: "myturn".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            turn + atk,
+            Turn {
+                action: vec![dbl_atk; 2],
+                bonus_action: vec![dbl_atk; 3],
+                once_on_hit: dmg,
+                name: "myturn".to_string(),
+                ..Default::default()
+            }
+        )
+    }
+
+    #[test]
+    fn test_turn_once_on_hit_fixed_one_attack() {
+        let turn = Turn {
+            action: vec![Attack {
+                hit: 20,
+                ..Default::default()
+            }],
+            once_on_hit: Damage {
+                fixed: 20,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(turn.expected_damage(0), 19.0);
+    }
+
+    #[test]
+    fn test_turn_once_on_hit_fixed_multiple_attacks() {
+        let turn = Turn {
+            action: vec![
+                Attack {
+                    hit: 0,
+                    ..Default::default()
+                };
+                2
+            ],
+            once_on_hit: Damage {
+                fixed: 20,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(
+            format!("{:.4}", turn.expected_damage(20)),
+            format!("{:.4}", 1.95)
+        );
+
+        let turn = Turn {
+            action: vec![
+                Attack {
+                    hit: 0,
+                    ..Default::default()
+                };
+                4
+            ],
+            once_on_hit: Damage {
+                fixed: 20,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(
+            format!("{:.2}", turn.expected_damage(20)),
+            format!("{:.2}", 3.71)
+        );
+    }
+
+    #[test]
+    fn test_turn_once_on_hit_crit_one_attack() {
+        let turn = Turn {
+            action: vec![Attack {
+                hit: 20,
+                ..Default::default()
+            }],
+            once_on_hit: Damage {
+                dmg: 20.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(turn.expected_damage(0), 20.0);
+    }
+
+    #[test]
+    fn test_turn_once_on_hit_crit_multiple_attacks() {
+        let turn = Turn {
+            action: vec![
+                Attack {
+                    hit: 0,
+                    ..Default::default()
+                };
+                2
+            ],
+            once_on_hit: Damage {
+                dmg: 20.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(
+            format!("{:.4}", turn.expected_damage(20)),
+            format!("{:.4}", 2.0 * 1.95)
+        );
+
+        let turn = Turn {
+            action: vec![
+                Attack {
+                    hit: 0,
+                    ..Default::default()
+                };
+                4
+            ],
+            once_on_hit: Damage {
+                dmg: 20.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(
+            format!("{:.2}", turn.expected_damage(20)),
+            format!("{:.2}", 2.0 * 3.71)
+        );
+    }
+
+    #[test]
+    fn test_turn_foe() {
+        let turn = Turn {
+            action: vec![Attack {
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+        .foe();
+
+        assert_eq!(
+            format!("{:.4}", turn.expected_damage(20)),
+            format!("{:.4}", (1.0 / 20.0) * 2.0 * d4)
+        )
+    }
+
+    #[test]
+    fn test_turn_colossus_slayer() {
+        let atk = Attack {
+            hit: 5,
+            dmg: "1d8+3".parse().unwrap(),
+            ..Default::default()
+        };
+        let turn = Turn {
+            action: vec![atk; 2],
+            ..Default::default()
+        };
+        let slayer = turn.foe().colossus_slayer();
+
+        assert_eq!(slayer.once_on_hit.dmg, d4 + d8);
+        assert_eq!(
+            slayer.once_on_hit.dice,
+            (Damage::dice(1, 4) + Damage::dice(1, 8)).dice
+        );
+        assert_eq!(slayer, turn.colossus_slayer().foe());
+
+        // one d8 and one d4 a turn however many attacks land, doubled when
+        // the first to land crits
+        let miss = 1.0 - atk.hit_chance(15, Roll::Normal) - 0.05;
+        let first_crit = 0.05 + miss * 0.05;
+        assert_eq!(
+            format!(
+                "{:.4}",
+                slayer.expected_damage(15) - turn.expected_damage(15)
+            ),
+            format!("{:.4}", (1.0 - miss * miss + first_crit) * (d4 + d8))
+        );
+
+        // which the distribution sees as dice
+        let pmf = slayer.distribution(15).unwrap();
+        assert_eq!(
+            format!("{:.4}", pmf.iter().map(|(d, p)| d * p).sum::<f64>()),
+            format!("{:.4}", slayer.expected_damage(15))
+        );
+    }
+
+    #[test]
+    fn test_turn_hexblades_curse() {
+        let atk = Attack {
+            hit: 5,
+            dmg: Damage {
+                dmg: d8,
+                fixed: 3,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let turn = Turn {
+            action: vec![atk],
+            bonus_action: vec![atk],
+            ..Default::default()
+        };
+        let cursed = turn.hexblades_curse(3);
+
+        for a in cursed.action.iter().chain(&cursed.bonus_action) {
+            assert_eq!(a.dmg.fixed, 6);
+            assert_eq!(a.crit_range, 19);
+        }
+
+        // hits on 10 to 18, crits on 19 and 20, the +3 not doubled
+        assert_eq!(
+            format!("{:.4}", cursed.expected_damage(15)),
+            format!("{:.4}", 2.0 * (9.0 / 20.0 * 10.5 + 2.0 / 20.0 * 15.0))
+        );
+
+        // a champion keeps the wider range
+        let champion = Turn {
+            action: vec![Attack {
+                crit_range: 18,
+                ..atk
+            }],
+            ..Default::default()
+        };
+        assert_eq!(champion.hexblades_curse(3).action[0].crit_range, 18);
+
+        // Horde Breaker's attack is at another creature, so isn't cursed
+        let breaker = turn.clone().horde_breaker(13);
+        let cursed = breaker.hexblades_curse(3);
+        assert_eq!(cursed.other_targets, breaker.other_targets);
+        assert_eq!(
+            format!(
+                "{:.4}",
+                cursed.expected_damage(15) - turn.hexblades_curse(3).expected_damage(15)
+            ),
+            format!(
+                "{:.4}",
+                breaker.expected_damage(15) - turn.expected_damage(15)
+            )
+        );
+    }
+
+    #[test]
+    fn test_turn_mark() {
+        let dummy = Turn {
+            action: vec![
+                Attack {
+                    ..Default::default()
+                };
+                2
+            ],
+            bonus_action: vec![
+                Attack {
+                    ..Default::default()
+                };
+                2
+            ],
+            name: "myturn".to_string(),
+            ..Default::default()
+        }
+        .mark();
+
+        assert_eq!(dummy.first_turn.bonus_action.len(), 0);
+        assert_eq!(
+            format!("{:.4}", dummy.first_turn.expected_damage(12)),
+            format!("{:.4}", d6)
+        );
+        assert_eq!(dummy.max_damage.name, "myturn");
+    }
+
+    #[test]
+    fn test_turn_mark_keeps_other_attacks() {
+        let atk = Attack {
+            hit: 5,
+            dmg: Damage::dice(1, 8),
+            ..Default::default()
+        };
+        let turn = Turn {
+            action: vec![atk],
+            bonus_action: vec![atk],
+            reaction: vec![atk],
+            triggered: vec![(Trigger::Hit, atk)],
+            ..Default::default()
+        };
+
+        let mark = turn.mark();
+
+        // only the bonus action goes to casting it
+        assert_eq!(
+            mark.first_turn,
+            Turn {
+                bonus_action: vec![],
+                ..mark.max_damage.clone()
+            }
+        );
+        assert_eq!(mark.first_turn.reaction.len(), 1);
+        assert_eq!(mark.first_turn.triggered.len(), 1);
+        assert!(
+            mark.first_turn.expected_damage(14)
+                > mark.unmodified.action[0].expected_damage(14) * 2.0
+        );
+    }
+
+    #[test]
+    fn test_turn_mark_calculations() {
+        let atk = Damage {
+            dmg: d10,
+            fixed: 5,
+            ..Default::default()
+        };
+        let crit = Damage {
+            dmg: d4,
+            fixed: 3,
+            ..Default::default()
+        };
+
+        let ac = 18;
+
+        let turn = Turn {
+            action: vec![
+                Attack {
+                    hit: 1,
+                    dmg: atk,
+                    crit: crit,
+                    ..Default::default()
+                };
+                3
+            ],
+            bonus_action: vec![
+                Attack {
+                    hit: 12,
+                    dmg: atk,
+                    crit: crit,
+                    ..Default::default()
+                };
+                2
+            ],
+            once_on_hit: Damage {
+                dmg: d10,
+                fixed: 4,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let mark = turn.mark();
+
+        let (max, rounds, deficit) = mark.breakeven(ac);
+
+        assert_eq!(mark.first_turn.bonus_action.len(), 0);
+
+        assert_eq!(
+            max.cmpable(),
+            (turn.clone()
+                + Damage {
+                    dmg: d6,
+                    ..Default::default()
+                })
+            .expected_damage(ac)
+            .cmpable()
+        );
+
+        assert_eq!(rounds, 4);
+
+        assert_eq!(deficit.cmpable(), -1921);
+    }
+
+    #[test]
+    fn test_turn_rounds_to_clear_one_enemy() {
+        // 10 expected damage per round at AC 11
+        let turn = Turn {
+            action: vec![Attack {
+                dmg: Damage {
+                    fixed: 20,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        assert_eq!(turn.rounds_to_clear(11, 25, 1, Overkill::Carry), 2.5);
+        assert_eq!(turn.rounds_to_clear(11, 25, 1, Overkill::Waste), 3.0);
+        assert_eq!(turn.rounds_to_clear(11, 20, 1, Overkill::Waste), 2.0);
+    }
+
+    #[test]
+    fn test_turn_rounds_to_clear_three_enemies() {
+        let turn = Turn {
+            action: vec![Attack {
+                dmg: Damage {
+                    fixed: 20,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        assert_eq!(turn.rounds_to_clear(11, 25, 3, Overkill::Carry), 7.5);
+        assert_eq!(turn.rounds_to_clear(11, 25, 3, Overkill::Waste), 9.0);
+
+        // no damage, never clears
+        assert_eq!(
+            Turn::default().rounds_to_clear(11, 25, 3, Overkill::Carry),
+            f64::INFINITY
+        );
+    }
+
+    #[test]
+    fn test_turn_auc() {
+        // 21 - ac between AC 2 and 20, 1.0 above that
+        let turn = Turn {
+            action: vec![Attack {
+                dmg: Damage {
+                    fixed: 20,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        assert_eq!(turn.auc(11..=15), 8.0);
+        // (2 + 1) / 2 + (1 + 1) / 2 over a width of 2
+        assert_eq!(turn.auc(19..=21), 1.25);
+        assert_eq!(turn.auc(15..=15), 6.0);
+    }
+
+    #[test]
+    fn test_turn_max_ac_for_possible_kill() {
+        // a crit's 16 plus a point for each the natural 20 beats AC by
+        let turn = Turn {
+            action: vec![Attack {
+                hit: 5,
+                dmg: Damage::dice(1, 8),
+                degrees: Some(1.0),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert_eq!(turn.max_damage(11), Some(30.0));
+
+        assert_eq!(turn.max_ac_for_possible_kill(30, 10..=30), Some(11));
+        assert_eq!(turn.max_ac_for_possible_kill(31, 10..=30), Some(10));
+        assert_eq!(turn.max_ac_for_possible_kill(16, 10..=30), Some(30));
+        assert_eq!(turn.max_ac_for_possible_kill(32, 10..=30), None);
+
+        let averaged = Turn {
+            action: vec![Attack {
+                dmg: Damage {
+                    dmg: d8,
+                    ..Default::default()
+                },
+                ..turn.action[0]
+            }],
+            ..Default::default()
+        };
+        assert_eq!(averaged.max_ac_for_possible_kill(1, 10..=30), None);
+    }
+
+    #[test]
+    fn test_turn_extra_attack_on_crit() {
+        let atk = Attack {
+            dmg: Damage {
+                fixed: 20,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let turn = Turn {
+            action: vec![atk],
+            ..Default::default()
+        };
+
+        let extra = turn.clone().extra_attack_on_crit(atk);
+
+        assert_eq!(
+            extra.expected_damage(11) - turn.expected_damage(11),
+            (1.0 / 20.0) * atk.expected_damage(11)
+        );
+
+        // more attacks, more chances to crit
+        let turn = Turn {
+            action: vec![atk; 2],
+            ..Default::default()
+        };
+
+        let extra = turn.clone().extra_attack_on_crit(atk);
+
+        assert_eq!(
+            format!(
+                "{:.4}",
+                extra.expected_damage(11) - turn.expected_damage(11)
+            ),
+            format!(
+                "{:.4}",
+                (1.0 - (19.0f64 / 20.0).powi(2)) * atk.expected_damage(11)
+            )
+        );
+    }
+
+    #[test]
+    fn test_turn_add_damage_triggered() {
+        let dmg = Damage {
+            dmg: 1.0,
+            fixed: 1,
+            ..Default::default()
+        };
+
+        let turn = Turn::default().extra_attack_on_crit(Attack::default()) + dmg;
+
+        assert_eq!(turn.triggered[0].1.dmg, dmg);
+    }
+
+    #[test]
+    fn test_on_hit_fraction() {
+        let atk = Attack {
+            dmg: Damage {
+                fixed: 20,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(atk.on_hit_fraction(11, 0.5), 5.0);
+
+        let turn = Turn {
+            action: vec![atk; 2],
+            ..Default::default()
+        };
+
+        assert_eq!(turn.on_hit_fraction(11, 0.5), 10.0);
+
+        // lifesteal from the weapon only, the Favored Foe rider doesn't qualify
+        let foe = turn.foe();
+        let qualifying = Turn {
+            once_on_hit: Damage::default(),
+            ..foe.clone()
+        };
+
+        assert_eq!(qualifying.on_hit_fraction(11, 0.5), 10.0);
+        assert!(foe.on_hit_fraction(11, 0.5) > qualifying.on_hit_fraction(11, 0.5));
+    }
+
+    #[test]
+    fn test_turn_riders_stackable() {
+        let sneak = Damage {
+            dmg: 3.0 * d6,
+            ..Default::default()
+        };
+        let slayer = Damage {
+            dmg: d8,
+            ..Default::default()
+        };
+
+        let turn = Turn {
+            action: vec![Attack::default(); 2],
+            ..Default::default()
+        };
+
+        let riders = turn
+            .clone()
+            .rider(OncePerTurnRider {
+                name: "sneak attack".to_string(),
+                dmg: sneak,
+                ..Default::default()
+            })
+            .rider(OncePerTurnRider {
+                name: "colossus slayer".to_string(),
+                dmg: slayer,
+                ..Default::default()
+            });
+
+        // same as the legacy once_on_hit with both summed
+        let legacy = Turn {
+            once_on_hit: sneak + slayer,
+            ..turn
+        };
+
+        assert_eq!(
+            format!("{:.4}", riders.expected_damage(15)),
+            format!("{:.4}", legacy.expected_damage(15))
+        );
+    }
+
+    #[test]
+    fn test_turn_riders_exclusive_crit_only() {
+        let exclusive = |name: &str, dmg, crit_only| OncePerTurnRider {
+            name: name.to_string(),
+            dmg,
+            crit_only,
+            stacking: Stacking::Exclusive("concentration".to_string()),
+        };
+
+        // the crit rider is better when it applies, the other when it can't
+        let turn = Turn {
+            action: vec![
+                Attack {
+                    hit: 5,
+                    dmg: Damage::dice(1, 8),
+                    crit_range: 19,
+                    ..Default::default()
+                };
+                2
+            ],
+            ..Default::default()
+        }
+        .rider(exclusive("hit", Damage::dice(1, 6), false))
+        .rider(exclusive("crit", Damage::dice(4, 8), true));
+
+        let pmf = turn.distribution(15).unwrap();
+        assert_eq!(
+            format!("{:.4}", turn.expected_damage(15)),
+            format!("{:.4}", pmf.iter().map(|(d, p)| d * p).sum::<f64>())
+        );
+    }
+
+    #[test]
+    fn test_turn_riders_exclusive() {
+        let big = Damage {
+            dmg: 2.0 * d8,
+            ..Default::default()
+        };
+        let small = Damage {
+            dmg: d4,
+            fixed: 1,
+            ..Default::default()
+        };
+
+        let turn = Turn {
+            action: vec![Attack::default(); 2],
+            ..Default::default()
+        };
+
+        let exclusive = |name: &str, dmg| OncePerTurnRider {
+            name: name.to_string(),
+            dmg,
+            stacking: Stacking::Exclusive("concentration".to_string()),
+            ..Default::default()
+        };
+
+        let riders = turn
+            .clone()
+            .rider(exclusive("small", small))
+            .rider(exclusive("big", big));
+
+        // only the better of the pair applies
+        let best = Turn {
+            once_on_hit: big,
+            ..turn.clone()
+        };
+
+        assert_eq!(riders.expected_damage(15), best.expected_damage(15));
+
+        // the compatibility shim still stacks on top
+        let shim = Turn {
+            once_on_hit: small,
+            ..riders.clone()
+        };
+        let both = Turn {
+            once_on_hit: big + small,
+            ..turn
+        };
+
+        assert_eq!(
+            format!("{:.4}", shim.expected_damage(15)),
+            format!("{:.4}", both.expected_damage(15))
+        );
+    }
+
+    #[test]
+    fn test_best_nova_target() {
+        let atk = Attack {
+            hit: 5,
+            dmg: Damage {
+                dmg: d8,
+                fixed: 3,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let sustained = Turn {
+            action: vec![atk; 2],
+            ..Default::default()
+        };
+        let nova = Turn {
+            action: vec![atk; 4],
+            ..Default::default()
+        };
+
+        let armored = Monster { ac: 20, hp: 60 };
+        let soft = Monster { ac: 12, hp: 60 };
+
+        // extra attacks land more often against the low AC
+        assert_eq!(
+            best_nova_target(&sustained, &nova, &[armored, soft]),
+            Some(1)
+        );
+        assert_eq!(
+            best_nova_target(&sustained, &nova, &[soft, armored]),
+            Some(0)
+        );
+
+        // but not when the sustained turn already drops it
+        let mook = Monster { ac: 12, hp: 5 };
+
+        assert_eq!(
+            best_nova_target(&sustained, &nova, &[mook, armored]),
+            Some(1)
+        );
+
+        assert_eq!(best_nova_target(&sustained, &nova, &[]), None);
+    }
+
+    #[test]
+    fn test_cumulative_damage() {
+        let turn = Turn {
+            action: vec![Attack {
+                hit: 7,
+                dmg: Damage {
+                    dmg: d8,
+                    fixed: 4,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let f = |x: f64| format!("{:.4}", x);
+
+        assert_eq!(
+            f(cumulative_damage(&turn, &turn, 15, 3)),
+            f(3.0 * turn.expected_damage(15))
+        );
+        assert_eq!(cumulative_damage(&turn, &turn, 15, 0), 0.0);
+
+        // an action surge nova up front
+        let surge = Turn {
+            action: vec![turn.action[0]; 2],
+            ..Default::default()
+        };
+        assert_eq!(
+            f(cumulative_damage(&surge, &turn, 15, 3)),
+            f(4.0 * turn.expected_damage(15))
+        );
+
+        // the mark catches up with the unmarked turn in the breakeven round
+        let dual = Turn {
+            bonus_action: vec![turn.action[0]],
+            ..turn.clone()
+        };
+        let mark = dual.mark();
+        let (_, rounds, _) = mark.breakeven(15);
+        let unmarked = |n| cumulative_damage(&dual, &dual, 15, n);
+
+        assert!(mark.cumulative_damage(15, rounds as u32) >= unmarked(rounds as u32));
+        assert!(mark.cumulative_damage(15, rounds as u32 - 1) < unmarked(rounds as u32 - 1));
+    }
+
+    #[test]
+    fn test_encounter_resources() {
+        let sustained = Turn {
+            action: vec![Attack {
+                hit: 7,
+                dmg: Damage {
+                    dmg: d8,
+                    fixed: 4,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let smite = |dice: f64| {
+            sustained.clone()
+                + Damage {
+                    dmg: dice * d8,
+                    fixed: 0,
+                    ..Default::default()
+                }
+        };
+
+        let resources = Resources {
+            pools: vec![
+                Pool {
+                    name: "1st level".to_string(),
+                    uses: 2,
+                    turn: smite(2.0),
+                },
+                Pool {
+                    name: "2nd level".to_string(),
+                    uses: 1,
+                    turn: smite(3.0),
+                },
+            ],
+        };
+
+        // the 2nd level slot goes first, then both 1st level slots
+        let mut greedy = Greedy;
+        let mut left = resources.clone();
+        let mut spent = vec![];
+        for round in 0..5 {
+            match greedy.choose(&left, 15, round) {
+                Some(i) => {
+                    assert!(left.spend(i));
+                    spent.push(left.pools[i].name.clone());
+                }
+                None => spent.push("none".to_string()),
+            }
+        }
+        assert_eq!(
+            spent,
+            ["2nd level", "1st level", "1st level", "none", "none"]
+        );
+        assert!(left.pools.iter().all(|p| p.uses == 0));
+
+        let dmg = |t: &Turn| t.expected_damage(15);
+        assert_eq!(
+            format!(
+                "{:.4}",
+                encounter_damage(&sustained, &mut resources.clone(), &mut Greedy, 15, 5)
+            ),
+            format!(
+                "{:.4}",
+                dmg(&smite(3.0)) + 2.0 * dmg(&smite(2.0)) + 2.0 * dmg(&sustained)
+            )
+        );
+
+        // a policy that keeps asking for the same pool can't overspend it
+        struct First;
+        impl SpendPolicy for First {
+            fn choose(&mut self, _: &Resources, _: i32, _: u32) -> Option<usize> {
+                Some(0)
+            }
+        }
+
+        let mut left = resources.clone();
+        assert_eq!(
+            format!(
+                "{:.4}",
+                encounter_damage(&sustained, &mut left, &mut First, 15, 4)
+            ),
+            format!("{:.4}", 2.0 * dmg(&smite(2.0)) + 2.0 * dmg(&sustained))
+        );
+        assert_eq!(left.pools[0].uses, 0);
+        assert_eq!(left.pools[1].uses, 1);
+        assert!(!left.spend(0));
+        assert!(!left.spend(2));
+    }
+
+    #[test]
+    fn test_expected_damage_weighted() {
+        let atk = Attack {
+            dmg: Damage {
+                fixed: 20,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(atk.expected_damage_weighted(&[(11, 1.0)]), 10.0);
+        // weights need not sum to 1
+        assert_eq!(atk.expected_damage_weighted(&[(11, 1.0), (15, 3.0)]), 7.0);
+        // nothing to weigh
+        assert_eq!(atk.expected_damage_weighted(&[]), 0.0);
+        assert_eq!(atk.expected_damage_weighted(&[(11, 0.0), (15, 0.0)]), 0.0);
+    }
+
+    #[test]
+    fn test_expected_damage_multi() {
+        let atk = Attack {
+            dmg: Damage {
+                fixed: 20,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(atk.expected_damage_multi(&[11]), 10.0);
+        assert_eq!(atk.expected_damage_multi(&[11, 15]), 10.0 + 6.0);
+        assert_eq!(atk.expected_damage_multi(&[]), 0.0);
+
+        let fireball = SaveAttack {
+            dc: 15,
+            save_bonus_of_target: 3,
+            dmg: Damage {
+                dmg: 8.0 * d6,
+                ..Default::default()
+            },
+            half_on_save: true,
+            ..Default::default()
+        };
+
+        // fails on 1 to 11 at +3 and 1 to 14 at +0
+        assert_eq!(
+            format!("{:.4}", fireball.expected_damage_multi(&[3, 0])),
+            format!(
+                "{:.4}",
+                (11.0 + 9.0 * 0.5) / 20.0 * 28.0 + (14.0 + 6.0 * 0.5) / 20.0 * 28.0
+            )
+        );
+    }
+
+    #[test]
+    fn test_expected_vs_cr() {
+        // 21 - ac for these ACs
+        let turn = Turn {
+            action: vec![Attack {
+                dmg: Damage {
+                    fixed: 20,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        // AC 13
+        assert_eq!(turn.expected_vs_cr(0.5), 8.0);
+        assert_eq!(turn.expected_vs_cr(3.0), 8.0);
+        // AC 17
+        assert_eq!(turn.expected_vs_cr(11.0), 4.0);
+
+        // AC 19
+        assert_eq!(turn.expected_vs_cr(20.0), 2.0);
+    }
+
+    #[test]
+    fn test_turn_first_hit_bonus() {
+        let bonus = Damage {
+            fixed: 20,
+            ..Default::default()
+        };
+        let atk = Attack {
+            first_hit_bonus: bonus,
+            ..Default::default()
+        };
+
+        // alone it behaves like any other damage
+        assert_eq!(atk.expected_damage(11), 10.0);
+
+        let turn = Turn {
+            action: vec![atk; 2],
+            ..Default::default()
+        };
+
+        // lands once if either copy hits, like a once_on_hit rider
+        let rider = Turn {
+            action: vec![Attack::default(); 2],
+            once_on_hit: bonus,
+            ..Default::default()
+        };
+
+        assert_eq!(turn.expected_damage(11), rider.expected_damage(11));
+        assert!(turn.expected_damage(11) < 2.0 * atk.expected_damage(11));
+
+        // a different weapon tracks its own first hit
+        let other = Attack { hit: 1, ..atk };
+        let turn = Turn {
+            action: vec![atk, other],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            turn.expected_damage(11),
+            atk.expected_damage(11) + other.expected_damage(11)
+        );
+    }
+
+    #[test]
+    fn test_turn_stacked_contributions() {
+        let atk = Attack {
+            hit: 3,
+            dmg: Damage {
+                dmg: d8,
+                fixed: 3,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let turn = Turn {
+            action: vec![atk; 2],
+            bonus_action: vec![atk.sharpshooter()],
+            ..Default::default()
+        }
+        .foe()
+        .extra_attack_on_crit(atk);
+
+        let stacked = turn.stacked_contributions(14);
+        let labels: Vec<&str> = stacked.iter().map(|(l, _)| l.as_str()).collect();
+
+        assert_eq!(
+            labels,
+            [
+                "action 1",
+                "action 2",
+                "bonus action 1",
+                "on crit 1",
+                "rider"
+            ]
+        );
+        assert_eq!(stacked[0].1, atk.expected_damage(14));
+        assert_eq!(
+            stacked.iter().map(|(_, d)| d).sum::<f64>(),
+            turn.expected_damage(14)
+        );
+    }
+
+    #[test]
+    fn test_turn_mastery_nick() {
+        let atk = Attack {
+            dmg: Damage {
+                fixed: 20,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let turn = Turn {
+            action: vec![atk],
+            ..Default::default()
+        }
+        .mastery(WeaponMastery::Nick, atk);
+
+        assert_eq!(turn.action.len(), 2);
+        assert_eq!(turn.bonus_action.len(), 0);
+        assert_eq!(turn.expected_damage(11), 2.0 * atk.expected_damage(11));
+    }
+
+    #[test]
+    fn test_turn_mastery_cleave() {
+        let atk = Attack {
+            dmg: Damage {
+                fixed: 20,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        for ac in [5, 11, 18] {
+            let turn = Turn {
+                action: vec![atk],
+                // the bonus action's hits don't let the weapon cleave
+                bonus_action: vec![Attack { hit: 10, ..atk }],
+                ..Default::default()
+            };
+            let cleave = turn.clone().mastery(WeaponMastery::Cleave(15), atk);
+
+            // second attack, at the second target, only when the first lands
+            let hit = atk.hit_chance(ac, Roll::Normal) + 1.0 / 20.0;
+
+            assert_eq!(
+                format!(
+                    "{:.4}",
+                    cleave.expected_damage(ac) - turn.expected_damage(ac)
+                ),
+                format!("{:.4}", hit * atk.expected_damage(15))
+            );
+
+            // Hunter's Mark is on the first target
+            let marked = |t: Turn| t.mark().max_damage.expected_damage(ac);
+            assert_eq!(
+                format!("{:.4}", marked(cleave.clone()) - marked(turn.clone())),
+                format!("{:.4}", hit * atk.expected_damage(15))
+            );
+        }
+
+        // and the distribution agrees
+        let dice = Turn {
+            action: vec![
+                Attack {
+                    hit: 5,
+                    dmg: "1d8+3".parse().unwrap(),
+                    ..Default::default()
+                };
+                2
+            ],
+            ..Default::default()
+        };
+        let cleave = dice
+            .clone()
+            .mastery(
+                WeaponMastery::Cleave(17),
+                Attack {
+                    hit: 5,
+                    dmg: Damage::dice(1, 8),
+                    ..Default::default()
+                },
+            )
+            .mark()
+            .max_damage;
+        let pmf = cleave.distribution(14).unwrap();
+        assert_eq!(
+            format!("{:.4}", pmf.iter().map(|(d, p)| d * p).sum::<f64>()),
+            format!("{:.4}", cleave.expected_damage(14))
+        );
+    }
+
+    #[test]
+    fn test_balancing_bonus() {
+        let atk = Attack {
+            dmg: Damage {
+                fixed: 10,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        // 10 expected damage at AC 11, one attack lands on average
+        let a = Turn {
+            action: vec![atk; 2],
+            ..Default::default()
+        };
+        // 13 expected damage
+        let b = Turn {
+            action: vec![Attack {
+                dmg: Damage {
+                    fixed: 26,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let bonus = balancing_bonus(&a, &b, 11);
+
+        assert_eq!(format!("{:.4}", bonus), "3.0000");
+        assert_eq!(
+            (a.clone()
+                + Damage {
+                    fixed: bonus as i32,
+                    ..Default::default()
+                })
+            .expected_damage(11),
+            b.expected_damage(11)
+        );
+
+        // and the other way around
+        assert_eq!(format!("{:.4}", balancing_bonus(&b, &a, 11)), "-6.0000");
+    }
+
+    #[test]
+    fn test_head_to_head() {
+        let f = |x: f64| format!("{:.4}", x);
+        let turn = |dmg: Damage| Turn {
+            action: vec![Attack {
+                hit: 5,
+                dmg,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        // both average 10.5 on a hit, the 3d6 bunched closer to it
+        let steady = turn(Damage::dice(3, 6));
+        let swingy = turn(Damage::dice(1, 20));
+        assert_eq!(f(steady.expected_damage(15)), f(swingy.expected_damage(15)));
+
+        // yet the steady build comes out ahead a little more often
+        let (wins, ties, losses) = head_to_head(&steady, &swingy, 15).unwrap();
+        assert_eq!(
+            (f(wins), f(ties), f(losses)),
+            (f(0.3931), f(0.2163), f(0.3906))
+        );
+
+        let (back_wins, back_ties, back_losses) = head_to_head(&swingy, &steady, 15).unwrap();
+        assert_eq!(
+            (f(back_wins), f(back_ties), f(back_losses)),
+            (f(losses), f(ties), f(wins))
+        );
+
+        // evenly matched against itself
+        let (wins, _, losses) = head_to_head(&steady, &steady, 15).unwrap();
+        assert_eq!(f(wins), f(losses));
+
+        assert_eq!(
+            head_to_head(&Turn::default(), &Turn::default(), 15),
+            Some((0.0, 1.0, 0.0))
+        );
+        let averaged = turn(Damage {
+            dmg: 10.0,
+            ..Default::default()
+        });
+        assert_eq!(head_to_head(&steady, &averaged, 15), None);
+    }
+
+    #[test]
+    fn test_concentration_save() {
+        assert_eq!(concentration_save(4.0, 5), 0.8);
+        assert_eq!(concentration_save(30.0, 5), 0.55);
+        assert_eq!(concentration_save(60.0, 0), 0.0);
+        assert_eq!(concentration_save(0.0, 10), 1.0);
+    }
+
+    #[test]
+    fn test_mark_under_fire() {
+        let turn = Turn {
+            action: vec![
+                Attack {
+                    hit: 7,
+                    dmg: Damage {
+                        dmg: d8,
+                        fixed: 4,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                };
+                2
+            ],
+            bonus_action: vec![Attack {
+                hit: 7,
+                dmg: Damage {
+                    dmg: d6,
+                    fixed: 0,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let mark = turn.mark();
+        let ac = 16;
+
+        let first = mark.first_turn.expected_damage(ac);
+        let base = mark.unmodified.expected_damage(ac);
+        let max = mark.max_damage.expected_damage(ac);
+
+        // nothing to lose on the casting round
+        assert_eq!(mark.expected_damage_under_fire(ac, 1, 50.0, 0), first);
+
+        // light blows, kept 80% of the time
+        let light = mark.expected_damage_under_fire(ac, 3, 4.0, 5);
+
+        assert_eq!(
+            format!("{:.4}", light),
+            format!(
+                "{:.4}",
+                first + (0.8 * max + 0.2 * base) + (0.64 * max + 0.36 * base)
+            )
+        );
+
+        // always broken by the first hit
+        let heavy = mark.expected_damage_under_fire(ac, 3, 60.0, 0);
+
+        assert_eq!(
+            format!("{:.4}", heavy),
+            format!("{:.4}", first + 2.0 * base)
+        );
+        assert!(heavy < light);
+    }
+
+    #[test]
+    fn test_turn_asi() {
+        let dmg = Damage {
+            dmg: d6,
+            fixed: 3,
+            ..Default::default()
+        };
+        let atk = Attack {
+            hit: 5,
+            dmg,
+            ..Default::default()
+        };
+
+        let turn = Turn {
+            action: vec![atk; 2],
+            bonus_action: vec![atk],
+            once_on_hit: dmg,
+            ..Default::default()
+        }
+        .asi();
+
+        for a in turn.action.iter().chain(turn.bonus_action.iter()) {
+            assert_eq!(a.hit, 6);
+            assert_eq!(
+                a.dmg,
+                Damage {
+                    dmg: d6,
+                    fixed: 4,
+                    ..Default::default()
+                }
+            );
+        }
+
+        assert_eq!(turn.once_on_hit, dmg);
+    }
+
+    #[test]
+    fn test_turn_expected_damage_multi_ac() {
+        let atk = Attack {
+            dmg: Damage {
+                fixed: 20,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let turn = Turn {
+            action: vec![atk; 2],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            turn.expected_damage_multi_ac(&[11, 11]),
+            Some(turn.expected_damage(11))
+        );
+        assert_eq!(
+            turn.expected_damage_multi_ac(&[11, 16]),
+            Some(atk.expected_damage(11) + atk.expected_damage(16))
+        );
+
+        // the rider lands if either attack hits its own target
+        let turn = Turn {
+            action: vec![Attack::default(); 2],
+            once_on_hit: Damage {
+                fixed: 20,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let miss = (1.0 - 10.0 / 20.0) * (1.0 - 5.0 / 20.0);
+
+        assert_eq!(
+            format!("{:.4}", turn.expected_damage_multi_ac(&[11, 16]).unwrap()),
+            format!("{:.4}", (1.0 - miss) * 20.0)
+        );
+    }
+
+    #[test]
+    fn test_turn_expected_damage_multi_ac_with() {
+        let f = |x: f64| format!("{:.4}", x);
+
+        let atk = Attack {
+            dmg: Damage {
+                fixed: 20,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let turn = Turn {
+            action: vec![atk; 2],
+            ..Default::default()
+        };
+
+        // only the prone AC 11 target's attack has advantage
+        let prone = |ac| ac < 13;
+        assert_eq!(
+            turn.expected_damage_multi_ac_with(&[11, 16], prone).map(f),
+            Some(f(
+                atk.expected_damage_with(11, Roll::Advantage) + atk.expected_damage(16)
+            ))
+        );
+        assert_eq!(
+            turn.expected_damage_multi_ac_with(&[11, 16], |_| false),
+            turn.expected_damage_multi_ac(&[11, 16])
+        );
+        assert_eq!(
+            turn.expected_damage_multi_ac_with(&[11, 11], |_| true)
+                .map(f),
+            Some(f(turn.expected_damage_with(11, Roll::Advantage)))
+        );
+
+        // the rider lands if either attack hits, the first with advantage
+        let turn = Turn {
+            action: vec![Attack::default(); 2],
+            once_on_hit: Damage {
+                fixed: 20,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let miss = (1.0 - 10.0 / 20.0) * (1.0 - 10.0 / 20.0) * (1.0 - 5.0 / 20.0);
+        assert_eq!(
+            turn.expected_damage_multi_ac_with(&[11, 16], prone).map(f),
+            Some(f((1.0 - miss) * 20.0))
+        );
+
+        assert_eq!(turn.expected_damage_multi_ac_with(&[11], prone), None);
+    }
+
+    #[test]
+    fn test_turn_expected_damage_multi_ac_mismatch() {
+        let turn = Turn {
+            action: vec![Attack::default(); 2],
+            ..Default::default()
+        };
+
+        assert_eq!(turn.expected_damage_multi_ac(&[11]), None);
+        assert_eq!(turn.expected_damage_multi_ac(&[11, 11, 11]), None);
+    }
+
+    #[test]
+    fn test_turn_horde_breaker() {
+        let longbow = Attack {
+            hit: 7,
+            dmg: Damage {
+                dmg: d8,
+                fixed: 4,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let turn = Turn {
+            action: vec![longbow; 2],
+            bonus_action: vec![Attack::default()],
+            ..Default::default()
+        };
+        let horde = turn.clone().horde_breaker(18);
+
+        assert_eq!(horde.action, turn.action);
+        assert_eq!(horde.bonus_action, turn.bonus_action);
+        assert_eq!(horde.other_targets, vec![(18, longbow)]);
+
+        // the extra arrow at the second target, AC 18
+        assert_eq!(
+            format!("{:.4}", horde.expected_damage(15)),
+            format!(
+                "{:.4}",
+                turn.expected_damage(15) + longbow.expected_damage(18)
+            )
+        );
+
+        // Favored Foe and Hunter's Mark stay on the first target
+        let ranger = |t: Turn| t.foe().mark().max_damage;
+        assert_eq!(
+            format!("{:.4}", ranger(horde.clone()).expected_damage(15)),
+            format!(
+                "{:.4}",
+                ranger(turn.clone()).expected_damage(15) + longbow.expected_damage(18)
+            )
+        );
+
+        // and the distribution agrees
+        let dice = Turn {
+            action: vec![Attack {
+                dmg: "1d8+4".parse().unwrap(),
+                ..longbow
+            }],
+            ..Default::default()
+        };
+        let marked = ranger(dice.horde_breaker(18));
+        let pmf = marked.distribution(15).unwrap();
+        assert_eq!(
+            format!("{:.4}", pmf.iter().map(|(d, p)| d * p).sum::<f64>()),
+            format!("{:.4}", marked.expected_damage(15))
+        );
+
+        assert_eq!(Turn::default().horde_breaker(18), Turn::default());
+    }
+
+    #[test]
+    fn test_turn_expected_damage_spread() {
+        let atk = Attack {
+            hit: 6,
+            dmg: Damage {
+                dmg: d6,
+                fixed: 4,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let turn = Turn {
+            action: vec![atk; 3],
+            bonus_action: vec![atk],
+            once_on_hit: Damage {
+                dmg: 2.0 * d6,
+                fixed: 0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        // the fourth attack wraps around to the first target
+        assert_eq!(
+            turn.expected_damage_spread(&[12, 16, 20]),
+            turn.expected_damage_multi_ac(&[12, 16, 20, 12])
+        );
+        assert_eq!(
+            turn.expected_damage_spread(&[16]),
+            Some(turn.expected_damage(16))
+        );
+        assert_eq!(turn.expected_damage_spread(&[]), None);
+
+        // the rider still only lands once
+        let (mut miss, mut first_crit) = (1.0, 0.0);
+        for ac in [12, 16, 20, 12] {
+            first_crit += miss / 20.0;
+            miss *= 1.0 - (atk.hit_chance(ac, Roll::Normal) + 1.0 / 20.0);
+        }
+        let attacks: f64 = [12, 16, 20, 12]
+            .iter()
+            .map(|&ac| atk.expected_damage(ac))
+            .sum();
+
+        assert_eq!(
+            format!("{:.4}", turn.expected_damage_spread(&[12, 16, 20]).unwrap()),
+            format!(
+                "{:.4}",
+                attacks + (1.0 - miss) * 2.0 * d6 + first_crit * 2.0 * d6
+            )
+        );
+    }
+
+    #[test]
+    fn test_fumble_cost() {
+        let atk = Attack {
+            hit: 10,
+            dmg: Damage {
+                dmg: d8,
+                fixed: 5,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        // only a natural 1 misses
+        assert_eq!(
+            format!("{:.4}", atk.fumble_cost(5)),
+            format!("{:.4}", 0.475)
+        );
+        assert_eq!(
+            format!("{:.4}", atk.fumble_cost(11)),
+            format!("{:.4}", 0.475)
+        );
+        // a 1 would miss anyway
+        assert_eq!(atk.fumble_cost(12), 0.0);
+        assert_eq!(atk.fumble_cost(25), 0.0);
+
+        let turn = Turn {
+            action: vec![atk; 2],
+            bonus_action: vec![atk.sharpshooter()],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            format!("{:.4}", turn.fumble_cost(5)),
+            format!("{:.4}", 3.0 * 0.475 + 0.5)
+        );
+        assert_eq!(turn.fumble_cost(20), 0.0);
+    }
+
+    #[test]
+    fn test_sensitivity() {
+        let atk = Attack {
+            hit: 7,
+            dmg: Damage {
+                dmg: d8,
+                fixed: 4,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let turn = Turn {
+            action: vec![
+                atk,
+                Attack {
+                    degrees: Some(1.0),
+                    first_hit_bonus: Damage {
+                        dmg: d6,
+                        fixed: 1,
+                        ..Default::default()
+                    },
+                    ..atk
+                },
+            ],
+            bonus_action: vec![Attack {
+                min_hit_damage: Some(20.0),
+                ..atk
+            }],
+            ..Default::default()
+        };
+
+        let hit = Attack {
+            hit: 1,
+            ..Default::default()
+        };
+        let fixed = Damage {
+            dmg: 0.0,
+            fixed: 1,
+            ..Default::default()
+        };
+
+        // change from one more point, clamped at both ends
+        for ac in 0..=35 {
+            let base = turn.expected_damage(ac);
+            let (per_hit, per_dmg) = turn.sensitivity(ac);
+
+            assert_eq!(
+                format!("{:.4}", per_hit),
+                format!("{:.4}", (turn.clone() + hit).expected_damage(ac) - base)
+            );
+            assert_eq!(
+                format!("{:.4}", per_dmg),
+                format!("{:.4}", (turn.clone() + fixed).expected_damage(ac) - base)
+            );
+        }
+
+        // the floored bonus action attack gains nothing from fixed damage
+        assert_eq!(turn.bonus_action[0].sensitivity(15).1, 0.0);
+        let (per_hit, per_dmg) = atk.sensitivity(15);
+        assert_eq!(per_hit, 8.5 / 20.0);
+        assert_eq!(format!("{:.4}", per_dmg), format!("{:.4}", 0.65));
+        assert_eq!(atk.sensitivity(30).0, 0.0);
+    }
+
+    #[test]
+    fn test_turn_bonus_on_all_miss() {
+        let atk = Attack {
+            hit: 3,
+            dmg: Damage {
+                dmg: d8,
+                fixed: 3,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let other = Attack { hit: 6, ..atk };
+
+        let turn = Turn {
+            action: vec![atk, other],
+            ..Default::default()
+        };
+        let bonus = turn.clone().bonus_on_all_miss(atk);
+
+        let ac = 15;
+        let miss = |a: &Attack| 1.0 - (a.hit_chance(ac, Roll::Normal) + 1.0 / 20.0);
+
+        assert_eq!(
+            format!(
+                "{:.4}",
+                bonus.expected_damage(ac) - turn.expected_damage(ac)
+            ),
+            format!("{:.4}", miss(&atk) * miss(&other) * atk.expected_damage(ac))
+        );
+
+        // and it can be the hit that lands the rider
+        let rider = Damage {
+            fixed: 10,
+            ..Default::default()
+        };
+        let turn = Turn {
+            once_on_hit: rider,
+            ..turn
+        };
+        let bonus = turn.clone().bonus_on_all_miss(atk);
+
+        assert_eq!(
+            format!(
+                "{:.4}",
+                bonus.expected_damage(ac) - turn.expected_damage(ac)
+            ),
+            format!(
+                "{:.4}",
+                miss(&atk) * miss(&other) * (atk.expected_damage(ac) + (1.0 - miss(&atk)) * 10.0)
+            )
+        );
+    }
+
+    #[test]
+    fn test_attack_expected_damage_rational() {
+        let crossbow = Attack {
+            hit: 12,
+            dmg: Damage {
+                dmg: 1.0 * d6,
+                fixed: 6,
+                ..Default::default()
+            },
+            crit: Damage {
+                dmg: 1.0 * d6,
+                fixed: 0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        // 8.125
+        assert_eq!(crossbow.expected_damage_rational(17), Some((65, 8)));
+        assert_eq!(crossbow.expected_damage(17), 65.0 / 8.0);
+
+        let atk = Attack {
+            hit: 3,
+            dmg: Damage {
+                dmg: d8,
+                fixed: 3,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let (num, den) = atk.expected_damage_rational(16).unwrap();
+
+        assert_eq!((num, den), (129, 40));
+        assert_eq!(num as f64 / den as f64, atk.expected_damage(16));
+
+        // thirds aren't exact
+        let atk = Attack {
+            dmg: Damage {
+                dmg: 1.0 / 3.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(atk.expected_damage_rational(10), None);
+    }
+
+    #[test]
+    fn test_attack_degrees() {
+        let atk = Attack {
+            hit: 5,
+            degrees: Some(1.0),
+            ..Default::default()
+        };
+
+        // AC 20: beats it by 0 on a 15 up to 5 on the natural 20
+        assert_eq!(atk.expected_damage(20), 15.0 / 20.0);
+        // AC 24: by 0 on a 19, 1 on the natural 20
+        assert_eq!(atk.expected_damage(24), 1.0 / 20.0);
+        // only the natural 20 can hit, it gets no margin
+        assert_eq!(atk.expected_damage(30), 0.0);
+
+        // on top of regular damage
+        let sword = Attack {
+            dmg: Damage {
+                dmg: d8,
+                fixed: 3,
+                ..Default::default()
+            },
+            degrees: Some(0.5),
+            ..atk
+        };
+
+        assert_eq!(
+            sword.expected_damage(20),
+            Attack {
+                degrees: None,
+                ..sword
+            }
+            .expected_damage(20)
+                + 0.5 * 15.0 / 20.0
+        );
+        assert_eq!(sword.expected_damage_rational(20).unwrap(), (57, 20));
+
+        // degrees add up like other bonuses
+        assert_eq!((atk + sword).degrees, Some(1.5));
+        assert_eq!((atk + Attack::default()).degrees, Some(1.0));
+    }
+
+    #[test]
+    #[cfg(feature = "simulate")]
+    fn test_turn_simulate() {
+        use rand::SeedableRng;
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(20);
+
+        let atk = Attack {
+            hit: 7,
+            dmg: Damage {
+                dmg: d8,
+                fixed: 4,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let mut builds = default_builds();
+        builds.extend([
+            // first hit and crit riders
+            Turn {
+                action: vec![atk; 2],
+                bonus_action: vec![Attack {
+                    crit_range: 19,
+                    crit_multiplier: 3,
+                    first_hit_bonus: Damage {
+                        dmg: d6,
+                        ..Default::default()
+                    },
+                    ..atk
+                }],
+                once_on_hit: Damage {
+                    dmg: 3.0 * d6,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }
+            .smite_on_crit(2)
+            .bless(),
+            // triggered attacks and saves
+            Turn {
+                action: vec![Attack {
+                    degrees: Some(0.5),
+                    nat20_rider: Some(Damage {
+                        dmg: d10,
+                        ..Default::default()
+                    }),
+                    ..atk
+                }],
+                saves: vec![SaveAttack {
+                    dc: 15,
+                    save_bonus_of_target: 3,
+                    dmg: Damage {
+                        dmg: 8.0 * d6,
+                        ..Default::default()
+                    },
+                    half_on_save: true,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }
+            .extra_attack_on_crit(atk)
+            .bonus_on_all_miss(atk),
+            // natural 1s rerolled
+            Turn {
+                action: vec![atk; 2],
+                ..Default::default()
+            }
+            .lucky(),
+            // a cleave at another target
+            Turn {
+                action: vec![atk; 2],
+                ..Default::default()
+            }
+            .mastery(WeaponMastery::Cleave(19), atk),
+        ]);
+
+        for turn in &builds {
+            for ac in [12, 17, 22] {
+                let expected = turn.expected_damage(ac);
+                let simulated = turn.simulate_with(ac, 100_000, &mut rng);
+
+                assert!(
+                    (simulated - expected).abs() < 0.01 * expected + 0.05,
+                    "{} at AC {}: {} simulated, {} expected",
+                    turn.name,
+                    ac,
+                    simulated,
+                    expected
+                );
+            }
+        }
+
+        assert!(Turn::default().simulate(15, 10) == 0.0);
+    }
+
+    #[test]
+    #[cfg(feature = "simulate")]
+    fn test_turn_simulate_dice() {
+        use rand::SeedableRng;
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(20);
+
+        let turn = Turn {
+            action: vec![
+                Attack {
+                    hit: 5,
+                    dmg: "1d8+3".parse().unwrap(),
+                    crit_range: 19,
+                    ..Default::default()
+                };
+                2
+            ],
+            ..Default::default()
+        }
+        .sneak_attack(2)
+        .savage_attacker(1, 8)
+        .foe();
+        let brutal = Turn {
+            action: vec![
+                Attack::builder()
+                    .hit(7)
+                    .dice(1, 8)
+                    .fixed(4)
+                    .build()
+                    .crit_die_override(12);
+                2
+            ],
+            ..Default::default()
+        };
+        let gwf = Turn {
+            action: vec![
+                Attack {
+                    hit: 7,
+                    dmg: Damage {
+                        fixed: 4,
+                        ..Damage::dice(2, 6)
+                    }
+                    .great_weapon_fighting(6),
+                    ..Default::default()
+                };
+                2
+            ],
+            ..Default::default()
+        };
+
+        for turn in [turn, brutal, gwf] {
+            // the damage spreads like the distribution, not just its mean
+            let mean = turn.expected_damage(15);
+            let variance = turn.variance(15).unwrap();
+
+            let rounds: Vec<f64> = (0..100_000)
+                .map(|_| turn.simulate_once(15, &mut rng))
+                .collect();
+            let sample_mean = rounds.iter().sum::<f64>() / rounds.len() as f64;
+            let sample_variance = rounds
+                .iter()
+                .map(|d| (d - sample_mean).powi(2))
+                .sum::<f64>()
+                / rounds.len() as f64;
+
+            assert!(
+                (sample_mean - mean).abs() < 0.01 * mean,
+                "{sample_mean} vs {mean}"
+            );
+            assert!(
+                (sample_variance - variance).abs() < 0.02 * variance,
+                "{sample_variance} vs {variance}"
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_turn_serde() {
+        let atk = Attack {
+            hit: 7,
+            dmg: Damage {
+                dmg: d8,
+                fixed: 4,
+                ..Default::default()
+            }
+            .of(DamageType::Slashing),
+            crit_range: 19,
+            nat20_rider: Some(Damage {
+                dmg: d10,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let turn = (Turn {
+            action: vec![atk; 2],
+            bonus_action: vec![Attack {
+                degrees: Some(0.5),
+                hit_die: Some(4),
+                ..atk
+            }],
+            triggered: vec![(Trigger::Crit, atk)],
+            saves: vec![SaveAttack {
+                dc: 15,
+                save_bonus_of_target: 3,
+                dmg: Damage {
+                    dmg: 8.0 * d6,
+                    ..Default::default()
+                },
+                half_on_save: true,
+                ..Default::default()
+            }],
+            name: "composed".to_string(),
+            ..Default::default()
+        } + Damage {
+            dmg: 0.1,
+            ..Default::default()
+        })
+        .sneak_attack(3)
+        .divine_smite(2);
+
+        let json = serde_json::to_string(&turn).unwrap();
+        let back: Turn = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(back, turn);
+        assert_eq!(
+            back.action[0].dmg.dmg.to_bits(),
+            turn.action[0].dmg.dmg.to_bits()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "config")]
+    fn test_builds_from_toml() {
+        assert_eq!(
+            builds_from_toml(include_str!("../builds.toml")),
+            Ok(default_builds())
+        );
+
+        let config = r#"
+            [attacks.archery]
+            hit = 2
+
+            [attacks.plusone]
+            hit = 1
+            fixed = 1
+
+            [attacks.rapier]
+            hit = 7
+            dice = [[1, 8]]
+            fixed = 4
+            crit_range = 19
+
+            [attacks.magic]
+            from = ["rapier", "plusone"]
+
+            [attacks.gwm]
+            from = ["magic", "archery"]
+            dice = [[2, 6]]
+            crit_dice = [[1, 4]]
+            great_weapon_master = true
+
+            [[turns]]
+            name = "duelist"
+            action = ["magic", "gwm"]
+        "#;
+
+        let magic = Attack {
+            hit: 8,
+            dmg: Damage {
+                fixed: 5,
+                ..Damage::dice(1, 8)
+            },
+            crit_range: 19,
+            ..Default::default()
+        };
+        let gwm = (magic
+            + Attack {
+                hit: 2,
+                dmg: Damage::dice(2, 6),
+                crit: Damage::dice(1, 4),
+                ..Default::default()
+            })
+        .great_weapon_master();
+
+        assert_eq!(
+            builds_from_toml(config),
+            Ok(vec![Turn {
+                action: vec![magic, gwm],
+                name: "duelist".to_string(),
+                ..Default::default()
+            }])
+        );
+
+        // errors
+        assert_eq!(
+            builds_from_toml("[[turns]]\naction = [\"nope\"]"),
+            Err(ParseError::UnknownAttack("nope".to_string()))
+        );
+        assert_eq!(
+            builds_from_toml(
+                "[attacks.a]\nfrom = [\"b\"]\n[attacks.b]\nfrom = [\"a\"]\n[[turns]]\naction = [\"a\"]"
+            ),
+            Err(ParseError::Cycle("a".to_string()))
+        );
+        assert!(matches!(
+            builds_from_toml("[attacks.a]\nhits = 2"),
+            Err(ParseError::Config(e)) if e.starts_with("line 2: unknown field `hits`")
+        ));
+        assert_eq!(
+            builds_from_toml("[attacks.a]\ndice = [[1, 0]]\n[[turns]]\naction = [\"a\"]"),
+            Err(ParseError::UnknownDie(0))
+        );
+        assert_eq!(
+            builds_from_toml("[attacks.a]\nhit = 2"),
+            Err(ParseError::Config("no turns to compare".to_string()))
+        );
+
+        // values the rules don't allow
+        let turn = "\n[[turns]]\naction = [\"a\"]";
+        assert_eq!(
+            builds_from_toml(&format!("[attacks.a]\ncrit_range = 1{turn}")),
+            Err(ParseError::OutOfRange("crit_range = 1".to_string()))
+        );
+        assert_eq!(
+            builds_from_toml(&format!("[attacks.a]\ncrit_range = 21{turn}")),
+            Err(ParseError::OutOfRange("crit_range = 21".to_string()))
+        );
+        assert_eq!(
+            builds_from_toml(&format!("[attacks.a]{turn}\nreaction_chance = 5.0")),
+            Err(ParseError::OutOfRange("reaction_chance = 5".to_string()))
+        );
+        assert_eq!(
+            builds_from_toml(&format!("[attacks.a]{turn}\nreaction_chance = -0.5")),
+            Err(ParseError::OutOfRange("reaction_chance = -0.5".to_string()))
+        );
+        assert_eq!(
+            builds_from_toml(&format!("[attacks.a]{turn}\nreaction_chance = nan")),
+            Err(ParseError::OutOfRange("reaction_chance = NaN".to_string()))
+        );
+        assert!(matches!(
+            builds_from_toml(&format!("[attacks.a]\ndice = [[-1, 6]]{turn}")),
+            Err(ParseError::Config(_))
+        ));
+        assert!(matches!(
+            builds_from_toml(&format!("[attacks.a]\ncrit_dice = [[2, -6]]{turn}")),
+            Err(ParseError::Config(_))
+        ));
+        assert!(builds_from_toml(&format!(
+            "[attacks.a]\ncrit_range = 2{turn}\nreaction_chance = 1.0"
+        ))
+        .is_ok());
+
+        // too many dice to count
+        let many = ParseError::OutOfRange("dice of a".to_string());
+        assert_eq!(
+            builds_from_toml(&format!(
+                "[attacks.a]\ndice = [[4294967295, 6], [1, 6]]{turn}"
+            )),
+            Err(many.clone())
+        );
+        assert_eq!(
+            builds_from_toml(&format!(
+                "[attacks.b]\ndice = [[4294967295, 6]]\n[attacks.a]\nfrom = [\"b\"]\ndice = [[1, 6]]{turn}"
+            )),
+            Err(many)
+        );
+        assert_eq!(
+            builds_from_toml("turns = []"),
+            Err(ParseError::Config("no turns to compare".to_string()))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "config")]
+    fn test_load_turns() {
+        assert_eq!(load_turns("builds.toml"), Ok(default_builds()));
+        assert!(matches!(
+            load_turns("no-such-builds.toml"),
+            Err(ParseError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_attack_min_hit_damage() {
+        let dagger = Attack {
+            dmg: Damage {
+                dmg: d4,
+                fixed: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let floored = Attack {
+            min_hit_damage: Some(5.0),
+            ..dagger
+        };
+
+        // 9 hitting faces and a crit at AC 11
+        assert_eq!(dagger.expected_damage(11), (9.0 * 3.5 + 6.0) / 20.0);
+        assert_eq!(floored.expected_damage(11), (9.0 * 5.0 + 7.5) / 20.0);
+
+        // no effect once the hit already beats the floor
+        let sword = Attack {
+            dmg: Damage {
+                dmg: d8,
+                fixed: 3,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Attack {
+                min_hit_damage: Some(5.0),
+                ..sword
+            }
+            .expected_damage(11),
+            sword.expected_damage(11)
+        );
+
+        // the higher floor wins
+        assert_eq!(
+            (floored
+                + Attack {
+                    min_hit_damage: Some(3.0),
+                    ..Default::default()
+                })
+            .min_hit_damage,
+            Some(5.0)
+        );
+    }
+
+    #[test]
+    fn test_build_at_level() {
+        let first = build_at_level(1);
+
+        assert_eq!(first.action.len(), 1);
+        assert_eq!(first.bonus_action.len(), 1);
+        assert_eq!(first.action[0].hit, 5);
+
+        // Extra Attack, Flurry of Blows, d6 Martial Arts, proficiency 3
+        let fifth = build_at_level(5);
+
+        assert_eq!(fifth.action.len(), 2);
+        assert_eq!(fifth.bonus_action.len(), 2);
+        assert_eq!(fifth.action[0].hit, 7);
+        assert_eq!(
+            fifth.action[0].dmg,
+            Damage {
+                fixed: 4,
+                ..Damage::dice(1, 6)
+            }
+            .of(DamageType::Bludgeoning)
+        );
+
+        let eleventh = build_at_level(11);
+
+        assert_eq!(
+            eleventh.action[0].dmg,
+            Damage {
+                fixed: 5,
+                ..Damage::dice(1, 8)
+            }
+            .of(DamageType::Bludgeoning)
+        );
+        assert_eq!(eleventh.action[0].dmg.dmg, d8);
+        assert!(eleventh.distribution(16).is_some());
+
+        let ac = 16;
+
+        assert!(first.expected_damage(ac) < fifth.expected_damage(ac));
+        assert!(fifth.expected_damage(ac) < eleventh.expected_damage(ac));
+    }
+
+    #[test]
+    fn test_dpr_by_level() {
+        let table = dpr_by_level(build_at_level, 16);
+
+        assert_eq!(table.len(), 20);
+        assert_eq!(table[4], (5, build_at_level(5).expected_damage(16)));
+
+        // never gets worse with levels
+        for pair in table.windows(2) {
+            assert!(pair[0].1 <= pair[1].1);
+        }
+    }
+
+    #[test]
+    fn test_turn_expected_damage_optimal_power_attack() {
+        let atk = Attack {
+            hit: 5,
+            dmg: Damage {
+                dmg: d6,
+                fixed: 3,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let turn = Turn {
+            action: vec![atk; 2],
+            bonus_action: vec![atk],
+            ..Default::default()
+        }
+        .foe();
+        let sharp = turn.clone() + Attack::default().sharpshooter();
+
+        assert_eq!(
+            turn.expected_damage_optimal_power_attack(18),
+            turn.expected_damage(18)
+        );
+        assert_eq!(
+            turn.expected_damage_optimal_power_attack(8),
+            sharp.expected_damage(8)
+        );
+    }
+
+    #[test]
+    fn test_turn_hit_count_distribution() {
+        let atk = Attack {
+            hit: 5,
+            ..Default::default()
+        };
+
+        // lands on an 11 or better
+        let turn = Turn {
+            action: vec![atk; 2],
+            ..Default::default()
+        };
+
+        assert_eq!(turn.hit_count_distribution(16), vec![0.25, 0.5, 0.25]);
+        assert_eq!(turn.expected_hits(16), 1.0);
+
+        let turn = Turn {
+            action: vec![atk; 2],
+            bonus_action: vec![atk.sharpshooter(), Attack { hit: 12, ..atk }],
+            ..Default::default()
+        };
+
+        for ac in [5, 14, 18, 25] {
+            let pmf = turn.hit_count_distribution(ac);
+
+            assert_eq!(pmf.len(), 5);
+            assert_eq!(
+                format!("{:.4}", pmf.iter().sum::<f64>()),
+                format!("{:.4}", 1.0)
+            );
+
+            let mean: f64 = pmf.iter().enumerate().map(|(n, p)| n as f64 * p).sum();
+
+            assert_eq!(
+                format!("{:.4}", mean),
+                format!("{:.4}", turn.expected_hits(ac))
+            );
+        }
+
+        assert_eq!(Turn::default().hit_count_distribution(10), vec![1.0]);
+    }
+
+    #[test]
+    fn test_turn_smite_on_crit() {
+        let atk = Attack {
+            hit: 7,
+            dmg: Damage {
+                dmg: d8,
+                fixed: 4,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let turn = Turn {
+            action: vec![atk; 2],
+            ..Default::default()
+        };
+        let dice = 2.0 * d8;
+
+        let always = turn.clone().rider(OncePerTurnRider {
+            name: "smite".to_string(),
+            dmg: Damage {
+                dmg: dice,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let held = turn.clone().smite_on_crit(2);
+
+        let ac = 16;
+        let base = turn.expected_damage(ac);
+        let crit = 1.0 - (19.0f64 / 20.0).powi(2);
+
+        assert_eq!(
+            format!("{:.4}", held.expected_damage(ac) - base),
+            format!("{:.4}", crit * 2.0 * dice)
+        );
+
+        // less damage overall, but more for each slot spent
+        let always_gain = always.expected_damage(ac) - base;
+        let held_gain = held.expected_damage(ac) - base;
+
+        let hit = 1.0 - (1.0 - turn.expected_hits(ac) / 2.0).powi(2);
+
+        assert!(held_gain < always_gain);
+        assert!(held_gain / crit > always_gain / hit);
+    }
+
+    #[test]
+    fn test_batch_expected_damage() {
+        let builds = default_builds();
+
+        let queries: Vec<(Turn, i32)> = (0..3)
+            .flat_map(|_| {
+                builds
+                    .iter()
+                    .flat_map(|t| (15..=18).map(|ac| (t.clone(), ac)))
+            })
+            .chain([(builds[1].foe(), 16)])
+            .collect();
+
+        let batch = batch_expected_damage(&queries);
+
+        assert_eq!(batch.len(), queries.len());
+
+        for ((turn, ac), dmg) in queries.iter().zip(batch) {
+            assert_eq!(dmg, turn.expected_damage(*ac));
+        }
+
+        assert!(batch_expected_damage(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_pareto_frontier() {
+        let build = |name: &str, hit, dmg| {
+            (
+                name.to_string(),
+                Turn {
+                    action: vec![Attack {
+                        hit,
+                        dmg,
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+            )
+        };
+
+        let builds = vec![
+            build(
+                "accurate",
+                11,
+                Damage {
+                    dmg: d6,
+                    fixed: 9,
+                    ..Default::default()
+                },
+            ),
+            build(
+                "heavy",
+                5,
+                Damage {
+                    dmg: 2.0 * d6,
+                    fixed: 13,
+                    ..Default::default()
+                },
+            ),
+            build(
+                "weak",
+                5,
+                Damage {
+                    dmg: d6,
+                    fixed: 3,
+                    ..Default::default()
+                },
+            ),
+        ];
+
+        // accuracy wins at high AC, damage at low
+        let (accurate, heavy) = (&builds[0].1, &builds[1].1);
+        assert!(accurate.expected_damage(22) > heavy.expected_damage(22));
+        assert!(accurate.expected_damage(12) < heavy.expected_damage(12));
+
+        assert_eq!(pareto_frontier(&builds, 12, 22), ["accurate", "heavy"]);
+
+        // identical builds don't dominate each other
+        let twins = vec![builds[2].clone(), builds[2].clone()];
+        assert_eq!(pareto_frontier(&twins, 12, 22).len(), 2);
+    }
+
+    #[test]
+    fn test_booming_blade() {
+        let rapier = Attack {
+            hit: 7,
+            dmg: Damage {
+                dmg: d8,
+                fixed: 4,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let thunder = |count| rapier.dmg + Damage::dice(count, 8).of(DamageType::Thunder);
+
+        assert_eq!(booming_blade(rapier, 1), rapier);
+        assert_eq!(booming_blade(rapier, 5).dmg, thunder(1));
+        assert_eq!(booming_blade(rapier, 11).dmg, thunder(2));
+        assert_eq!(booming_blade(rapier, 17).dmg, thunder(3));
+        assert_eq!(booming_blade(rapier, 17).dmg.dmg, 4.0 * d8);
+
+        // the cantrip dice double on a crit
+        let ac = 16;
+
+        assert_eq!(
+            format!(
+                "{:.4}",
+                booming_blade(rapier, 5).expected_damage(ac) - rapier.expected_damage(ac)
+            ),
+            format!(
+                "{:.4}",
+                rapier.hit_chance(ac, Roll::Normal) * d8 + (1.0 / 20.0) * 2.0 * d8
+            )
+        );
+
+        // the thunder is dice of its type
+        let rolled = booming_blade(
+            Attack {
+                dmg: "1d8+4".parse().unwrap(),
+                ..rapier
+            },
+            5,
+        );
+        assert_eq!(rolled.max_damage(ac), Some(2.0 * 8.0 + 4.0 + 2.0 * 8.0));
+        assert!(rolled.variance(ac).is_some());
+        let resistant = Defenses {
+            resistant: vec![DamageType::Thunder],
+            ..Default::default()
+        };
+        assert_eq!(
+            format!("{:.4}", rolled.expected_damage_vs(ac, &resistant)),
+            format!(
+                "{:.4}",
+                rolled.expected_damage(ac)
+                    - 0.5
+                        * (booming_blade(rapier, 5).expected_damage(ac)
+                            - rapier.expected_damage(ac))
+            )
+        );
+    }
+
+    #[test]
+    fn test_attack_nat20_rider() {
+        let atk = Attack {
+            hit: 5,
+            dmg: Damage {
+                dmg: d8,
+                fixed: 3,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let rider = Attack {
+            nat20_rider: Some(Damage {
+                dmg: d10,
+                fixed: 2,
+                ..Default::default()
+            }),
+            ..atk
+        };
+
+        // same chance at any AC, and not doubled
+        for ac in [5, 15, 30] {
+            assert_eq!(
+                format!("{:.4}", rider.expected_damage(ac) - atk.expected_damage(ac)),
+                format!("{:.4}", (d10 + 2.0) / 20.0)
+            );
+        }
+
+        // 10/20 * 7.5 + 1/20 * 12 + 1/20 * 7.5
+        assert_eq!(rider.expected_damage_rational(15), Some((189, 40)));
+    }
+
+    #[test]
+    #[cfg(feature = "plot")]
+    fn test_plot_dpr() {
+        let path = std::env::temp_dir().join("dndamage_test_plot_dpr.svg");
+
+        crate::plot_dpr(&default_builds(), 15..=22, &path).unwrap();
+
+        let svg = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("sharp xbow"));
+    }
+}
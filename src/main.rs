@@ -1,404 +1,196 @@
-#![allow(dead_code)]
+use std::fmt::Write;
+use std::io::IsTerminal;
+use std::ops::RangeInclusive;
 
-// --- Dice ---
+use dndamage::{default_builds, uncmp, Convert2Cmp, ExpectedDamage, HuntersMark, Turn};
 
-// return the expected_value of a die roll with the given number of faces
-macro_rules! expected_value {
-    ($n:literal) => {
-        paste::item! {
-            #[allow(non_upper_case_globals)]
-            const [<d $n>]:f32 =
-                (1.0 + $n as f32) / 2.0 ;
-        }
-    };
-}
-
-expected_value!(4);
-expected_value!(6);
-expected_value!(8);
-expected_value!(10);
-expected_value!(12);
-
-// --- Traits ---
-
-trait ExpectedDamage {
-    fn expected_damage(&self, ac: i32) -> f32;
-}
-
-// --- Types ---
-
-#[derive(Default, Debug, Copy, Clone, PartialEq)]
-struct Damage {
-    dmg: f32,
-    // not multiplied on crit
-    fixed: i32,
-}
+// --- Rendering ---
 
+// how the damage per AC is printed
 #[derive(Default, Debug, Copy, Clone, PartialEq)]
-struct Attack {
-    // bonus to hit chance
-    hit: i32,
-
-    dmg: Damage,
-    crit: Damage,
-}
-
-#[derive(Default, Debug, Clone, PartialEq)]
-struct Turn {
-    action: Vec<Attack>,
-    bonus_action: Vec<Attack>,
-
-    once_on_hit: Damage,
-    name: String,
-}
-
-struct HuntersMark {
-    unmodified: Turn,
-    first_turn: Turn,
-    max_damage: Turn,
-}
-
-// --- Methods ---
-
-impl Damage {
-    fn hit(&self) -> f32 {
-        self.dmg + self.fixed as f32
-    }
-
-    // critical hit doubles non-fixed damage
-    fn crit(&self) -> f32 {
-        self.dmg + self.hit()
-    }
-}
-
-impl Attack {
-    // excludes natural 20, treats natural 1 as a miss
-    fn hit_chance(&self, ac: i32) -> f32 {
-        18.min(0.max(20 + self.hit - ac)) as f32 / 20.0
-    }
-
-    fn sharpshooter(&self) -> Attack {
-        *self
-            + Attack {
-                hit: -5,
-                dmg: Damage {
-                    fixed: 10,
-                    ..Default::default()
-                },
-                ..Default::default()
-            }
-    }
-}
-
-impl Turn {
-    // Favored Foe
-    fn foe(&self) -> Turn {
-        Turn {
-            action: self.action.clone(),
-            bonus_action: self.bonus_action.clone(),
-
-            once_on_hit: self.once_on_hit
-                + Damage {
-                    dmg: d4,
-                    ..Default::default()
-                },
-            name: self.name.clone(),
-        }
-    }
-
-    // Hunter's Mark
-    fn mark(&self) -> HuntersMark {
-        let bonus = Damage {
-            dmg: d6,
-            ..Default::default()
-        };
-
-        let mark = self.clone() + bonus;
-
-        HuntersMark {
-            unmodified: self.clone(),
-            first_turn: Turn {
-                action: mark.action.clone(),
-                once_on_hit: self.once_on_hit,
-                ..Default::default()
-            },
-            max_damage: mark,
-        }
-    }
-}
-
-impl HuntersMark {
-    fn breakeven(&self, ac: i32) -> (f32, i32, f32) {
-        let base = self.unmodified.expected_damage(ac);
-        let first = self.first_turn.expected_damage(ac);
-        let max = self.max_damage.expected_damage(ac);
-
-        (
-            max,
-            1 + ((base - first) / (max - base)).ceil() as i32,
-            first - base,
-        )
-    }
-}
-
-// --- Trait Methods ---
-
-use core::ops::Add;
-
-impl Add for Damage {
-    type Output = Self;
-
-    fn add(self, other: Self) -> Self {
-        Self {
-            dmg: self.dmg + other.dmg,
-            fixed: self.fixed + other.fixed,
-        }
-    }
+enum Format {
+    #[default]
+    Table,
+    Csv,
+    Json,
+    Markdown,
 }
 
-impl Add for Attack {
-    type Output = Self;
-
-    fn add(self, other: Self) -> Self {
-        Attack {
-            hit: self.hit + other.hit,
-            dmg: self.dmg + other.dmg,
-            crit: self.crit + other.crit,
-        }
-    }
+// command line options
+#[derive(Debug, Clone, PartialEq)]
+struct Options {
+    format: Format,
+    color: bool,
+    // Favored Foe and Hunter's Mark columns
+    ranger: bool,
+    // table rows
+    ac_min: i32,
+    ac_max: i32,
+    ac_step: usize,
+    // TOML file of builds to compare instead of the defaults
+    config: Option<String>,
 }
 
-impl Add<Damage> for Attack {
-    type Output = Self;
-
-    fn add(self, dmg: Damage) -> Self {
-        Attack {
-            hit: self.hit,
-            dmg: self.dmg + dmg,
-            crit: self.crit,
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            format: Format::Table,
+            color: false,
+            ranger: true,
+            ac_min: 15,
+            ac_max: 22,
+            ac_step: 1,
+            config: None,
         }
     }
 }
 
-impl Add<Attack> for Turn {
-    type Output = Self;
-
-    fn add(self, atk: Attack) -> Self {
-        Turn {
-            action: self.action.clone().into_iter().map(|a| a + atk).collect(),
-            bonus_action: self
-                .bonus_action
-                .clone()
-                .into_iter()
-                .map(|a| a + atk)
-                .collect(),
-            once_on_hit: self.once_on_hit,
-            name: self.name.clone(),
-        }
+impl Options {
+    fn acs(&self) -> impl Iterator<Item = i32> {
+        (self.ac_min..=self.ac_max).step_by(self.ac_step)
     }
 }
 
-impl Add<Damage> for Turn {
-    type Output = Self;
-
-    fn add(self, dmg: Damage) -> Self {
-        Turn {
-            action: self.action.clone().into_iter().map(|a| a + dmg).collect(),
-            bonus_action: self
-                .bonus_action
-                .clone()
-                .into_iter()
-                .map(|a| a + dmg)
-                .collect(),
-            once_on_hit: self.once_on_hit,
-            name: self.name.clone(),
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--color" => options.color = true,
+            "--no-ranger" => options.ranger = false,
+            "--format" => {
+                options.format = match args.next().as_deref() {
+                    Some("table") => Format::Table,
+                    Some("csv") => Format::Csv,
+                    Some("json") => Format::Json,
+                    Some("markdown") => Format::Markdown,
+                    Some(other) => return Err(format!("unknown format: {other}")),
+                    None => return Err("--format needs table, csv, json or markdown".to_string()),
+                }
+            }
+            "--config" => options.config = Some(args.next().ok_or("--config needs a file")?),
+            "--ac-min" | "--ac-max" | "--ac-step" => {
+                let value = args.next().ok_or(format!("{arg} needs a value"))?;
+                let bad = |_| format!("bad value for {arg}: {value}");
+
+                match arg.as_str() {
+                    "--ac-min" => options.ac_min = value.parse().map_err(bad)?,
+                    "--ac-max" => options.ac_max = value.parse().map_err(bad)?,
+                    _ => options.ac_step = value.parse().map_err(bad)?,
+                }
+            }
+            _ => return Err(format!("unknown argument {arg}")),
         }
     }
-}
 
-impl ExpectedDamage for Attack {
-    fn expected_damage(&self, ac: i32) -> f32 {
-        self.hit_chance(ac) * self.dmg.hit() + (1.0 / 20.0) * (self.dmg.crit() + self.crit.crit())
+    if options.ac_step == 0 {
+        return Err("--ac-step must be positive".to_string());
     }
-}
-
-impl ExpectedDamage for Turn {
-    fn expected_damage(&self, ac: i32) -> f32 {
-        let mut total = 0.0;
-        let mut miss = 1.0;
-        let mut first_crit = 0.0;
-
-        let crit_chance = 1.0 / 20.0;
-
-        for d in self.action.iter().chain(self.bonus_action.iter()) {
-            total += d.expected_damage(ac);
-            first_crit += crit_chance * miss;
-            miss *= 1.0 - (d.hit_chance(ac) + crit_chance);
-        }
-
-        total += (1.0 - miss) * self.once_on_hit.hit();
-        total += first_crit * self.once_on_hit.dmg;
-
-        total
+    if options.ac_min > options.ac_max {
+        return Err(format!(
+            "--ac-min {} is above --ac-max {}",
+            options.ac_min, options.ac_max
+        ));
     }
-}
 
-// --- Util ---
-trait Convert2Cmp {
-    fn cmpable(&self) -> i32;
+    Ok(options)
 }
 
-impl Convert2Cmp for f32 {
-    fn cmpable(&self) -> i32 {
-        (100.0 * *self).trunc() as i32
+// float formatting
+const PREC: usize = 2;
+const WIDTH: usize = 2 + PREC + 2; // 2 for sign and decimal point
+
+// cells within this much damage of the max are highlighted as near ties
+const NEAR_TIE: f64 = 0.5;
+
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+
+// damage with marker for the max valued column, optionally colored
+fn dmg_cell(dmg: f64, max: i32, color: bool) -> String {
+    let is_max = max == dmg.cmpable();
+    let cell = format!(
+        "{}{:>width$.prec$}",
+        if is_max { ">" } else { " " },
+        dmg,
+        width = WIDTH,
+        prec = PREC
+    );
+
+    if !color {
+        cell
+    } else if is_max {
+        format!("{GREEN}{cell}{RESET}")
+    } else if uncmp(max) - dmg <= NEAR_TIE {
+        format!("{YELLOW}{cell}{RESET}")
+    } else {
+        cell
     }
 }
 
-fn uncmp(x: i32) -> f32 {
-    x as f32 / 100.0
-}
-
 // --- Methods ---
 
-fn main() {
-    // attack base
-    let dex = Attack {
-        hit: 5,
-        dmg: Damage {
-            fixed: 5,
-            ..Default::default()
-        },
-        ..Default::default()
-    };
-
-    let proficiency_bonus = Attack {
-        hit: 4,
-        ..Default::default()
-    };
-
-    let monk = dex + proficiency_bonus;
-
-    // attack modifiers
-    let archery = Attack {
-        hit: 2,
-        ..Default::default()
-    };
-
-    let deft_strike = Attack {
-        crit: Damage {
-            dmg: d6,
-            ..Default::default()
-        },
-        ..Default::default()
-    };
-
-    let plusone = Attack {
-        hit: 1,
-        dmg: Damage {
-            fixed: 1,
-            ..Default::default()
-        },
-        ..Default::default()
-    };
-
-    // weapons
-    let crossbow = Attack {
-        dmg: Damage {
-            dmg: d6,
-            ..Default::default()
-        },
-        ..Default::default()
-    };
-
-    let longsword = Attack {
-        dmg: Damage {
-            dmg: d10,
-            ..Default::default()
-        },
-        ..Default::default()
-    };
+// damage per build and AC. with the ranger columns, Favored Foe damage
+// and Hunter's Mark breakeven info
+fn render_table(turns: &[Turn], acs: impl Iterator<Item = i32>, options: &Options) -> String {
+    let mut out = String::new();
+    let acs: Vec<i32> = acs.collect();
 
-    let unarmed = Attack {
-        dmg: Damage {
-            dmg: d6,
-            ..Default::default()
-        },
-        ..Default::default()
-    };
-
-    // attacks
-    let crossbow = monk + archery + crossbow + plusone + deft_strike;
-    let sharp = crossbow.sharpshooter();
-    let longsword = monk + longsword + plusone + deft_strike;
-    let unarmed = monk + unarmed;
-
-    // turns
-    let crossbow = Turn {
-        action: vec![crossbow; 2],
-        bonus_action: vec![crossbow],
-        name: "xbow".to_string(),
-        ..Default::default()
+    let foe_turns: Vec<Turn> = if options.ranger {
+        turns.iter().map(|x| x.foe()).collect()
+    } else {
+        turns.to_vec()
     };
+    let mark_turns: Vec<HuntersMark> = turns.iter().map(|x| x.mark()).collect();
 
-    let sharp = Turn {
-        action: vec![sharp; 2],
-        bonus_action: vec![sharp],
-        name: "sharp xbow".to_string(),
-        ..Default::default()
-    };
+    let prec = PREC;
+    let width = WIDTH;
 
-    let melee = Turn {
-        action: vec![longsword; 2],
-        bonus_action: vec![unarmed; 2],
-        name: "sword&flurry".to_string(),
-        ..Default::default()
+    // leading and trailing space, max marker/separating space, one float,
+    // and for ranger 2 more floats & one int
+    let natural = if options.ranger {
+        2 + 3 * (width + 1) + 2
+    } else {
+        2 + (width + 1)
     };
-
-    // what is compared
-    let turns = [crossbow, sharp, melee];
-
-    let foe_turns: Vec<Turn> = turns.iter().map(|x| x.foe()).collect();
-    let mark_turns: Vec<HuntersMark> = turns.iter().map(|x| x.mark()).collect();
-
-    // float formatting
-    let prec = 2;
-    let width = 2 + prec + 2; // 2 for sign and decimal point
+    let longest = turns.iter().map(|t| t.name.len()).max().unwrap_or(0);
+    let w = natural.max(longest + 2);
+    let pad = w - natural;
 
     // header
     {
-        // leading and trailing space, max marker/separating space, 3 floats & one int
-        let w = 2 + 3 * (width + 1) + 2;
-
-        print!(" AC  ");
-        print!("|{:^w$}", "xbow");
-        print!("|{:^w$}", "sharp xbow");
-        print!("|{:^w$}", "sword/flurry",);
-        println!();
+        write!(out, " AC  ").unwrap();
+        for t in turns {
+            write!(out, "|{:^w$}", t.name).unwrap();
+        }
+        writeln!(out).unwrap();
 
-        println!("{:-<wi$}", "-", wi = 5 + turns.len() * (1 + w));
+        writeln!(out, "{:-<wi$}", "-", wi = 5 + turns.len() * (1 + w)).unwrap();
     }
 
-    for i in 15..=22 {
+    for &i in &acs {
         // AC
-        print!(" {:>2} ", i);
+        write!(out, " {:>2} ", i).unwrap();
 
-        let foe_dmg: Vec<f32> = foe_turns.iter().map(|t| t.expected_damage(i)).collect();
+        let foe_dmg: Vec<f64> = foe_turns.iter().map(|t| t.expected_damage(i)).collect();
         let mark_dmg: Vec<_> = mark_turns.iter().map(|h| h.breakeven(i)).collect();
 
         let max_foe = foe_dmg.iter().map(|x| x.cmpable()).max().unwrap();
         let max_mark = mark_dmg.iter().map(|(x, _, _)| x.cmpable()).max().unwrap();
 
         for i in 0..foe_dmg.len() {
-            // foe damage with marker for the max valued column
-            print!(
-                " | {}{:>width$.prec$}",
-                if max_foe == foe_dmg[i].cmpable() {
-                    ">"
-                } else {
-                    " "
-                },
-                foe_dmg[i]
-            );
+            write!(
+                out,
+                " | {:pad$}{}",
+                "",
+                dmg_cell(foe_dmg[i], max_foe, options.color)
+            )
+            .unwrap();
+
+            if !options.ranger {
+                continue;
+            }
 
             /* extra info for the max value mark column:
             if it is also the max foe column (sign is negative), how much damage
@@ -408,12 +200,12 @@ fn main() {
             */
             if max_mark == mark_dmg[i].0.cmpable() {
                 if max_foe == foe_dmg[i].cmpable() {
-                    print!(" {:>+width$.prec$}", mark_dmg[i].2);
+                    write!(out, " {:>+width$.prec$}", mark_dmg[i].2).unwrap();
                 } else {
-                    print!(" {:>+width$.prec$}", mark_dmg[i].0 - uncmp(max_foe));
+                    write!(out, " {:>+width$.prec$}", mark_dmg[i].0 - uncmp(max_foe)).unwrap();
                 }
             } else {
-                print!(" {:width$}", "");
+                write!(out, " {:width$}", "").unwrap();
             }
 
             /* if Hunter's Mark for this column doesn't beat the max
@@ -422,601 +214,549 @@ fn main() {
             how many rounds it takes to offset the first round loss of
             bonus action attacks */
             if mark_dmg[i].0.cmpable() < max_foe {
-                print!(" {:>width$} {0:1}", "");
+                write!(out, " {:>width$} {0:1}", "").unwrap();
             } else {
-                print!(
+                write!(
+                    out,
                     " {:>+width$.prec$} {}",
                     mark_dmg[i].0 - foe_dmg[i],
                     mark_dmg[i].1,
-                );
+                )
+                .unwrap();
             }
         }
-        println!();
+        writeln!(out).unwrap();
     }
-}
-
-// --- Tests ---
-
-#[cfg(test)]
-mod tests {
-    use crate::d10;
-    use crate::d4;
-    use crate::d6;
-    use crate::Attack;
-    use crate::Convert2Cmp;
-    use crate::Damage;
-    use crate::ExpectedDamage;
-    use crate::Turn;
 
-    // Dice
-
-    #[test]
-    fn test_dice() {
-        assert_eq!(d4, 2.5);
-        assert_eq!(d10, 5.5);
+    let shown = match (acs.first(), acs.last()) {
+        (Some(&lo), Some(&hi)) => lo..=hi,
+        _ => return out,
+    };
+    for (power, plain, ac) in power_attack_notes(turns, shown, options) {
+        match ac {
+            Some(ac) => writeln!(out, "{power} beats {plain} up to AC {ac}").unwrap(),
+            None => writeln!(out, "{power} never beats {plain}").unwrap(),
+        }
     }
 
-    // Damage
-
-    #[test]
-    fn test_dmg_hit() {
-        let dmg = Damage { dmg: 1.0, fixed: 1 };
+    out
+}
 
-        assert_eq!(dmg.hit(), 2.0);
+// for each build that is another's attacks with Sharpshooter or Great Weapon
+// Master, the AC of the range up to which it does at least as much damage,
+// with Favored Foe as in the table's cells
+fn power_attack_notes(
+    turns: &[Turn],
+    acs: RangeInclusive<i32>,
+    options: &Options,
+) -> Vec<(String, String, Option<i32>)> {
+    let mut notes = vec![];
+
+    for plain in turns {
+        let power = plain.sharpshooter();
+
+        for t in turns {
+            if t.action == power.action
+                && t.bonus_action == power.bonus_action
+                && t.action != plain.action
+            {
+                let cell = if options.ranger {
+                    plain.foe()
+                } else {
+                    plain.clone()
+                };
+                let ac = cell.power_attack_threshold(acs.clone());
+                notes.push((t.name.clone(), plain.name.clone(), ac));
+            }
+        }
     }
 
-    #[test]
-    fn test_dmg_crit() {
-        let dmg = Damage { dmg: 1.0, fixed: 1 };
-
-        assert_eq!(dmg.crit(), 3.0);
-    }
+    notes
+}
 
-    #[test]
-    fn test_dmg_add() {
-        assert_eq!(
-            Damage { dmg: 1.0, fixed: 1 } + Damage { dmg: 1.0, fixed: 1 },
-            Damage { dmg: 2.0, fixed: 2 }
-        );
+// a field quoted when it holds a comma or quote
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
     }
+}
 
-    // Attack
-
-    #[test]
-    fn test_attack_add() {
-        let dmg = Damage { dmg: 1.0, fixed: 1 };
-        let dbl_dmg = Damage { dmg: 2.0, fixed: 2 };
-
-        let atk = Attack {
-            hit: 1,
-            dmg: dmg,
-            crit: dmg,
-        };
+// column names, matching csv_row
+fn csv_header(turns: &[Turn], options: &Options) -> String {
+    let mut fields = vec!["ac".to_string()];
 
-        assert_eq!(
-            atk + atk,
-            Attack {
-                hit: 2,
-                dmg: dbl_dmg,
-                crit: dbl_dmg
+    for t in turns {
+        fields.push(csv_field(&t.name));
+        if options.ranger {
+            for col in ["mark", "mark rounds", "mark first round"] {
+                fields.push(csv_field(&format!("{} {col}", t.name)));
             }
-        );
+        }
     }
 
-    #[test]
-    fn test_attack_add_damage() {
-        let dmg = Damage { dmg: 1.0, fixed: 1 };
-        let dbl_dmg = Damage { dmg: 2.0, fixed: 2 };
-
-        let atk = Attack {
-            hit: 1,
-            dmg: dmg,
-            crit: dmg,
-        };
+    fields.join(",")
+}
 
-        assert_eq!(
-            atk + dmg,
-            Attack {
-                hit: 1,
-                dmg: dbl_dmg,
-                crit: dmg
-            }
-        );
+// one AC of the table: damage per build, with the ranger columns Favored Foe
+// damage and Hunter's Mark damage, breakeven rounds and first round change
+fn csv_row(turns: &[Turn], ac: i32, options: &Options) -> String {
+    let mut fields = vec![ac.to_string()];
+
+    for t in turns {
+        if options.ranger {
+            let (mark, rounds, first) = t.mark().breakeven(ac);
+
+            fields.push(format!("{:.PREC$}", t.foe().expected_damage(ac)));
+            fields.push(format!("{mark:.PREC$}"));
+            fields.push(rounds.to_string());
+            fields.push(format!("{first:.PREC$}"));
+        } else {
+            fields.push(format!("{:.PREC$}", t.expected_damage(ac)));
+        }
     }
 
-    #[test]
-    fn test_attack_sharpshooter() {
-        let dmg = Damage { dmg: 1.0, fixed: 1 };
-        let atk = Attack {
-            hit: 10,
-            dmg: dmg,
-            crit: dmg,
-        };
+    fields.join(",")
+}
 
-        let sharp = Attack {
-            hit: 5,
-            dmg: Damage {
-                dmg: 1.0,
-                fixed: 11,
-            },
-            crit: dmg,
-        };
+fn render_csv(turns: &[Turn], acs: impl Iterator<Item = i32>, options: &Options) -> String {
+    let mut out = csv_header(turns, options) + "\n";
 
-        assert_eq!(atk.sharpshooter(), sharp);
+    for ac in acs {
+        out += &csv_row(turns, ac, options);
+        out += "\n";
     }
 
-    #[test]
-    fn test_attack_fixed_dmg() {
-        let atk = Attack {
-            dmg: Damage {
-                dmg: 0.0,
-                fixed: 20,
-            },
-            ..Default::default()
-        };
+    out
+}
 
-        assert_eq!(atk.expected_damage(11), 10.0);
-        assert_eq!(atk.expected_damage(20), 1.0);
-        assert_eq!(atk.expected_damage(21), 1.0);
+// column names and the right aligned separator row, matching markdown_row
+fn markdown_header(turns: &[Turn], options: &Options) -> String {
+    let mut names = vec!["AC".to_string()];
 
-        assert_eq!(atk.expected_damage(2), 19.0);
+    for t in turns {
+        names.push(t.name.clone());
+        if options.ranger {
+            names.push(format!("{} mark", t.name));
+        }
     }
 
-    #[test]
-    //#[ignore]
-    fn test_attack_fumble() {
-        let atk = Attack {
-            dmg: Damage {
-                dmg: 0.0,
-                fixed: 20,
-            },
-            ..Default::default()
-        };
-        assert_eq!(atk.expected_damage(1), 19.0);
-    }
+    format!(
+        "| {} |\n|{}|",
+        names.join(" | "),
+        vec![" ---: "; names.len()].join("|")
+    )
+}
 
-    #[test]
-    fn test_attack_hit_bonus() {
-        let atk = Attack {
-            hit: 5,
-            dmg: Damage {
-                dmg: 0.0,
-                fixed: 20,
-            },
-            ..Default::default()
-        };
+// one AC of the table, the max damage in bold. the mark columns hold the
+// same Hunter's Mark info as the text table
+fn markdown_row(turns: &[Turn], ac: i32, options: &Options) -> String {
+    let foe_dmg: Vec<f64> = turns
+        .iter()
+        .map(|t| {
+            if options.ranger {
+                t.foe().expected_damage(ac)
+            } else {
+                t.expected_damage(ac)
+            }
+        })
+        .collect();
+    let mark_dmg: Vec<_> = turns.iter().map(|t| t.mark().breakeven(ac)).collect();
 
-        assert_eq!(atk.expected_damage(11), 15.0);
-        assert_eq!(atk.expected_damage(21), 5.0);
-    }
+    let max_foe = foe_dmg.iter().map(|x| x.cmpable()).max().unwrap();
+    let max_mark = mark_dmg.iter().map(|(x, _, _)| x.cmpable()).max().unwrap();
 
-    #[test]
-    fn test_attack_dmg_crit() {
-        let atk = Attack {
-            dmg: Damage {
-                dmg: 20.0,
-                fixed: 0,
-            },
-            ..Default::default()
-        };
+    let mut cells = vec![ac.to_string()];
 
-        assert_eq!(atk.expected_damage(11), 11.0);
+    for (foe, mark) in foe_dmg.iter().zip(&mark_dmg) {
+        let cell = format!("{foe:.PREC$}");
+        cells.push(if foe.cmpable() == max_foe {
+            format!("**{cell}**")
+        } else {
+            cell
+        });
 
-        let atk = Attack {
-            dmg: Damage {
-                dmg: 20.0,
-                fixed: 20,
-            },
-            ..Default::default()
-        };
+        if !options.ranger {
+            continue;
+        }
 
-        assert_eq!(atk.expected_damage(11), 21.0);
+        let mut info = vec![];
+        if max_mark == mark.0.cmpable() {
+            let extra = if max_foe == foe.cmpable() {
+                mark.2
+            } else {
+                mark.0 - uncmp(max_foe)
+            };
+            info.push(format!("{extra:+.PREC$}"));
+        }
+        if mark.0.cmpable() >= max_foe {
+            info.push(format!("{:+.PREC$}", mark.0 - foe));
+            info.push(mark.1.to_string());
+        }
+        cells.push(info.join(" "));
     }
 
-    #[test]
-    fn test_attack_crit_only_dmg() {
-        let atk = Attack {
-            crit: Damage {
-                dmg: 10.0,
-                fixed: 0,
-            },
-            ..Default::default()
-        };
-
-        assert_eq!(atk.expected_damage(11), 1.0);
+    format!("| {} |", cells.join(" | "))
+}
 
-        let atk = Attack {
-            crit: Damage {
-                dmg: 0.0,
-                fixed: 20,
-            },
-            ..Default::default()
-        };
+fn render_markdown(turns: &[Turn], acs: impl Iterator<Item = i32>, options: &Options) -> String {
+    let mut out = markdown_header(turns, options) + "\n";
 
-        assert_eq!(atk.expected_damage(11), 1.0);
+    for ac in acs {
+        out += &markdown_row(turns, ac, options);
+        out += "\n";
     }
 
-    #[test]
-    fn test_dmg_dpr_calc() {
-        let crossbow = crate::Attack {
-            hit: 12,
-            dmg: Damage {
-                dmg: 1.0 * d6,
-                fixed: 6,
-            },
-            crit: Damage {
-                dmg: 1.0 * d6,
-                fixed: 0,
-            },
-        };
+    out
+}
 
-        // https://rpgbot.net/dnd5/tools/dpr-calculator/
-        assert_eq!(crossbow.expected_damage(17), 8.125);
-
-        let rando = crate::Attack {
-            hit: 8,
-            dmg: Damage {
-                dmg: 2.0 * d6,
-                fixed: 5,
-            },
-            crit: Damage {
-                dmg: 1.0 * d4,
-                fixed: 3,
-            },
-        };
+// one build at one AC in the JSON output
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct JsonBuild {
+    name: String,
+    favored_foe: f64,
+    // Hunter's Mark damage, breakeven rounds and first round change
+    hunters_mark: (f64, i32, f64),
+}
 
-        assert_eq!(rando.expected_damage(16), 8.55)
-    }
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct JsonRow {
+    ac: i32,
+    builds: Vec<JsonBuild>,
+}
 
-    // Turn
+#[cfg(feature = "serde")]
+fn render_json(turns: &[Turn], acs: impl Iterator<Item = i32>) -> Result<String, String> {
+    let rows: Vec<JsonRow> = acs
+        .map(|ac| JsonRow {
+            ac,
+            builds: turns
+                .iter()
+                .map(|t| JsonBuild {
+                    name: t.name.clone(),
+                    favored_foe: t.foe().expected_damage(ac),
+                    hunters_mark: t.mark().breakeven(ac),
+                })
+                .collect(),
+        })
+        .collect();
 
-    #[test]
-    fn test_turn_actions() {
-        let atk = Attack {
-            dmg: Damage {
-                dmg: 20.0,
-                fixed: 20,
-            },
-            ..Default::default()
-        };
+    serde_json::to_string_pretty(&rows)
+        .map(|json| json + "\n")
+        .map_err(|e| e.to_string())
+}
 
-        let turn = Turn {
-            action: vec![atk],
-            ..Default::default()
-        };
+#[cfg(not(feature = "serde"))]
+fn render_json(_turns: &[Turn], _acs: impl Iterator<Item = i32>) -> Result<String, String> {
+    Err("--format json needs the serde feature".to_string())
+}
 
-        assert_eq!(turn.expected_damage(11), atk.expected_damage(11));
+// builds from a config file
+#[cfg(feature = "config")]
+fn load_builds(path: &str) -> Result<Vec<Turn>, String> {
+    dndamage::load_turns(path).map_err(|e| format!("{path}: {e}"))
+}
 
-        let turn = Turn {
-            action: vec![atk; 2],
-            ..Default::default()
-        };
+#[cfg(not(feature = "config"))]
+fn load_builds(_path: &str) -> Result<Vec<Turn>, String> {
+    Err("--config needs the config feature".to_string())
+}
 
-        assert_eq!(turn.expected_damage(11), 2.0 * atk.expected_damage(11));
-    }
+fn main() {
+    let mut options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(2);
+        }
+    };
 
-    #[test]
-    fn test_turn_bonus_actions() {
-        let atk = Attack {
-            dmg: Damage {
-                dmg: 20.0,
-                fixed: 20,
-            },
-            ..Default::default()
-        };
+    let turns = match &options.config {
+        Some(path) => load_builds(path).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            std::process::exit(2);
+        }),
+        None => default_builds(),
+    };
 
-        let turn = Turn {
-            bonus_action: vec![atk],
-            ..Default::default()
-        };
+    // only color when writing to a terminal
+    options.color &= std::io::stdout().is_terminal();
+
+    let out = match options.format {
+        Format::Table => render_table(&turns, options.acs(), &options),
+        Format::Csv => render_csv(&turns, options.acs(), &options),
+        Format::Markdown => render_markdown(&turns, options.acs(), &options),
+        Format::Json => render_json(&turns, options.acs()).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            std::process::exit(2);
+        }),
+    };
 
-        assert_eq!(turn.expected_damage(11), atk.expected_damage(11));
+    print!("{out}");
+}
 
-        let turn = Turn {
-            bonus_action: vec![atk; 2],
-            ..Default::default()
-        };
+// --- Tests ---
 
-        assert_eq!(turn.expected_damage(11), 2.0 * atk.expected_damage(11));
-    }
+#[cfg(test)]
+mod tests {
+    use crate::csv_header;
+    use crate::csv_row;
+    use crate::dmg_cell;
+    use crate::markdown_header;
+    use crate::markdown_row;
+    use crate::parse_args;
+    use crate::power_attack_notes;
+    use crate::render_table;
+    use crate::Format;
+    use crate::Options;
+    use dndamage::default_builds;
+    use dndamage::Convert2Cmp;
+    use dndamage::ExpectedDamage;
+    use dndamage::Turn;
 
     #[test]
-    fn test_turn_actions_and_bonus_actions() {
-        let atk = Attack {
-            dmg: Damage {
-                dmg: 20.0,
-                fixed: 20,
-            },
-            ..Default::default()
-        };
+    fn test_dmg_cell_color() {
+        let max = 12.5f64.cmpable();
 
-        let turn = Turn {
-            action: vec![atk; 2],
-            bonus_action: vec![atk; 2],
-            ..Default::default()
-        };
+        assert_eq!(dmg_cell(12.5, max, false), "> 12.50");
+        assert_eq!(dmg_cell(12.5, max, true), "\x1b[32m> 12.50\x1b[0m");
+
+        // near tie
+        assert_eq!(dmg_cell(12.25, max, false), "  12.25");
+        assert_eq!(dmg_cell(12.25, max, true), "\x1b[33m  12.25\x1b[0m");
 
-        assert_eq!(turn.expected_damage(11), 4.0 * atk.expected_damage(11));
+        // neither
+        assert_eq!(dmg_cell(9.0, max, true), "   9.00");
     }
 
     #[test]
-    fn test_turn_add_damage() {
-        let dmg = Damage { dmg: 1.0, fixed: 1 };
-        let dbl_dmg = Damage { dmg: 2.0, fixed: 2 };
-
-        let atk = Attack {
-            hit: 1,
-            dmg: dmg,
-            crit: dmg,
-        };
-
-        let doublish_atk = Attack {
-            hit: 1,
-            dmg: dbl_dmg,
-            crit: dmg,
-        };
-
-        let turn = Turn {
-            action: vec![atk; 2],
-            bonus_action: vec![atk; 3],
-            once_on_hit: dmg,
-            name: "myturn".to_string(),
-        };
-
+    fn test_render_table_snapshot() {
         assert_eq!(
-            turn + dmg,
-            Turn {
-                action: vec![doublish_atk; 2],
-                bonus_action: vec![doublish_atk; 3],
-                once_on_hit: dmg,
-                name: "myturn".to_string(),
-            }
-        )
+            render_table(&default_builds(), 15..=22, &Options::default()),
+            include_str!("snapshots/default_table.txt")
+        );
     }
 
     #[test]
-    fn test_turn_add_attack() {
-        let dmg = Damage { dmg: 1.0, fixed: 1 };
-        let dbl_dmg = Damage { dmg: 2.0, fixed: 2 };
-
-        let atk = Attack {
-            hit: 1,
-            dmg: dmg,
-            crit: dmg,
-        };
+    fn test_parse_args() {
+        let args = |a: &[&str]| parse_args(a.iter().map(|s| s.to_string()));
 
-        let dbl_atk = Attack {
-            hit: 2,
-            dmg: dbl_dmg,
-            crit: dbl_dmg,
-        };
+        assert_eq!(args(&[]), Ok(Options::default()));
+        assert_eq!(
+            args(&["--no-ranger", "--color"]),
+            Ok(Options {
+                color: true,
+                ranger: false,
+                ..Default::default()
+            })
+        );
 
-        let turn = Turn {
-            action: vec![atk; 2],
-            bonus_action: vec![atk; 3],
-            once_on_hit: dmg,
-            name: "myturn".to_string(),
-        };
+        // AC range
+        let options = args(&["--ac-min", "12", "--ac-max", "25", "--ac-step", "4"]).unwrap();
+        assert_eq!(options.acs().collect::<Vec<_>>(), vec![12, 16, 20, 24]);
+        assert_eq!(
+            Options::default().acs().collect::<Vec<_>>(),
+            (15..=22).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            args(&["--ac-min", "18", "--ac-max", "18"])
+                .unwrap()
+                .acs()
+                .count(),
+            1
+        );
 
+        // invalid
+        assert!(args(&["--ac-min"]).is_err());
+        assert!(args(&["--ac-min", "high"]).is_err());
+        assert!(args(&["--ac-step", "-1"]).is_err());
+        assert!(args(&["--ac-step", "0"]).is_err());
+        assert!(args(&["--ac-min", "23"]).is_err());
+        assert!(args(&["--config"]).is_err());
+        assert!(args(&["--format", "xml"]).is_err());
         assert_eq!(
-            turn + atk,
-            Turn {
-                action: vec![dbl_atk; 2],
-                bonus_action: vec![dbl_atk; 3],
-                once_on_hit: dmg,
-                name: "myturn".to_string(),
-            }
-        )
-    }
+            args(&["--ac-mn", "30"]).err(),
+            Some("unknown argument --ac-mn".to_string())
+        );
 
-    #[test]
-    fn test_turn_once_on_hit_fixed_one_attack() {
-        let turn = Turn {
-            action: vec![Attack {
-                hit: 20,
-                ..Default::default()
-            }],
-            once_on_hit: Damage {
-                fixed: 20,
-                ..Default::default()
-            },
-            ..Default::default()
-        };
+        assert_eq!(args(&["--format", "csv"]).unwrap().format, Format::Csv);
+        assert_eq!(args(&["--format", "json"]).unwrap().format, Format::Json);
+        assert_eq!(
+            args(&["--format", "markdown"]).unwrap().format,
+            Format::Markdown
+        );
 
-        assert_eq!(turn.expected_damage(0), 19.0);
+        assert_eq!(
+            args(&["--config", "builds.toml"]).unwrap().config,
+            Some("builds.toml".to_string())
+        );
     }
 
     #[test]
-    fn test_turn_once_on_hit_fixed_multiple_attacks() {
-        let turn = Turn {
-            action: vec![
-                Attack {
-                    hit: 0,
-                    ..Default::default()
-                };
-                2
-            ],
-            once_on_hit: Damage {
-                fixed: 20,
-                ..Default::default()
-            },
+    fn test_power_attack_notes() {
+        let builds = default_builds();
+        let options = Options::default();
+        let plain = Options {
+            ranger: false,
             ..Default::default()
         };
 
+        // only ACs in the range are considered
+        assert_eq!(
+            power_attack_notes(&builds, options.ac_min..=options.ac_max, &options),
+            vec![("sharp xbow".to_string(), "xbow".to_string(), Some(22))]
+        );
+        assert_eq!(
+            power_attack_notes(&builds, 1..=30, &plain),
+            vec![("sharp xbow".to_string(), "xbow".to_string(), Some(23))]
+        );
+        assert_eq!(
+            power_attack_notes(&builds, 24..=30, &plain),
+            vec![("sharp xbow".to_string(), "xbow".to_string(), None)]
+        );
         assert_eq!(
-            format!("{:.4}", turn.expected_damage(20)),
-            format!("{:.4}", 1.95)
+            power_attack_notes(&[builds[0].clone(), builds[2].clone()], 1..=30, &plain),
+            vec![]
         );
 
-        let turn = Turn {
-            action: vec![
-                Attack {
-                    hit: 0,
-                    ..Default::default()
-                };
-                4
-            ],
-            once_on_hit: Damage {
-                fixed: 20,
-                ..Default::default()
-            },
+        // Great Weapon Master is the same -5/+10
+        let melee = &builds[2];
+        let gwm = Turn {
+            action: melee
+                .action
+                .iter()
+                .map(|a| a.great_weapon_master())
+                .collect(),
+            bonus_action: melee
+                .bonus_action
+                .iter()
+                .map(|a| a.great_weapon_master())
+                .collect(),
+            name: "gwm".to_string(),
             ..Default::default()
         };
-
+        let notes = power_attack_notes(&[gwm.clone(), melee.clone()], 1..=30, &plain);
+        assert_eq!(notes.len(), 1);
         assert_eq!(
-            format!("{:.2}", turn.expected_damage(20)),
-            format!("{:.2}", 3.71)
+            (notes[0].0.as_str(), notes[0].1.as_str()),
+            ("gwm", "sword/flurry")
         );
+
+        let ac = notes[0].2.unwrap();
+        assert!(gwm.expected_damage(ac) >= melee.expected_damage(ac));
+        assert!(gwm.expected_damage(ac + 1) < melee.expected_damage(ac + 1));
     }
 
     #[test]
-    fn test_turn_once_on_hit_crit_one_attack() {
-        let turn = Turn {
-            action: vec![Attack {
-                hit: 20,
-                ..Default::default()
-            }],
-            once_on_hit: Damage {
-                dmg: 20.0,
-                ..Default::default()
-            },
+    fn test_csv() {
+        let builds = default_builds();
+        let plain = Options {
+            ranger: false,
             ..Default::default()
         };
 
-        assert_eq!(turn.expected_damage(0), 20.0);
+        assert_eq!(
+            csv_header(&builds, &plain),
+            "ac,xbow,sharp xbow,sword/flurry"
+        );
+        assert_eq!(csv_row(&builds, 15, &plain), "15,27.22,39.60,32.75");
+
+        assert_eq!(
+            csv_header(&builds[..1], &Options::default()),
+            "ac,xbow,xbow mark,xbow mark rounds,xbow mark first round"
+        );
+        assert_eq!(
+            csv_row(&builds, 15, &Options::default()),
+            "15,29.86,37.20,2,-2.43,42.18,46.95,3,-8.30,35.40,44.30,2,-7.15"
+        );
+        assert_eq!(
+            csv_row(&builds, 20, &Options::default()),
+            "20,22.68,27.45,2,-1.80,27.18,29.70,3,-5.18,25.34,30.80,2,-4.65"
+        );
+
+        // names are quoted as needed
+        let mut quoted = builds[..1].to_vec();
+        quoted[0].name = "xbow, \"sharp\"".to_string();
+        assert_eq!(csv_header(&quoted, &plain), "ac,\"xbow, \"\"sharp\"\"\"");
     }
 
     #[test]
-    fn test_turn_once_on_hit_crit_multiple_attacks() {
-        let turn = Turn {
-            action: vec![
-                Attack {
-                    hit: 0,
-                    ..Default::default()
-                };
-                2
-            ],
-            once_on_hit: Damage {
-                dmg: 20.0,
-                ..Default::default()
-            },
+    fn test_markdown() {
+        let builds = default_builds();
+        let plain = Options {
+            ranger: false,
             ..Default::default()
         };
 
         assert_eq!(
-            format!("{:.4}", turn.expected_damage(20)),
-            format!("{:.4}", 2.0 * 1.95)
+            markdown_header(&builds, &plain),
+            "| AC | xbow | sharp xbow | sword/flurry |\n| ---: | ---: | ---: | ---: |"
+        );
+        assert_eq!(
+            markdown_row(&builds, 15, &plain),
+            "| 15 | 27.22 | **39.60** | 32.75 |"
         );
 
-        let turn = Turn {
-            action: vec![
-                Attack {
-                    hit: 0,
-                    ..Default::default()
-                };
-                4
-            ],
-            once_on_hit: Damage {
-                dmg: 20.0,
-                ..Default::default()
-            },
-            ..Default::default()
-        };
-
         assert_eq!(
-            format!("{:.2}", turn.expected_damage(20)),
-            format!("{:.2}", 2.0 * 3.71)
+            markdown_header(&builds[..1], &Options::default()),
+            "| AC | xbow | xbow mark |\n| ---: | ---: | ---: |"
+        );
+        // same cells as the text table
+        assert_eq!(
+            markdown_row(&builds, 15, &Options::default()),
+            "| 15 | 29.86 |  | **42.18** | -8.30 +4.77 3 | 35.40 | +8.90 2 |"
         );
     }
 
     #[test]
-    fn test_turn_foe() {
-        let turn = Turn {
-            action: vec![Attack {
-                ..Default::default()
-            }],
-            ..Default::default()
-        }
-        .foe();
+    #[cfg(feature = "serde")]
+    fn test_render_json() {
+        let builds = default_builds();
+        let json = crate::render_json(&builds, 15..=15).unwrap();
+        let rows: serde_json::Value = serde_json::from_str(&json).unwrap();
 
-        assert_eq!(
-            format!("{:.4}", turn.expected_damage(20)),
-            format!("{:.4}", (1.0 / 20.0) * 2.0 * d4)
-        )
-    }
+        let rows = rows.as_array().unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["ac"], 15);
 
-    #[test]
-    fn test_turn_mark() {
-        let dummy = Turn {
-            action: vec![
-                Attack {
-                    ..Default::default()
-                };
-                2
-            ],
-            bonus_action: vec![
-                Attack {
-                    ..Default::default()
-                };
-                2
-            ],
-            name: "myturn".to_string(),
-            ..Default::default()
-        }
-        .mark();
+        let row = rows[0]["builds"].as_array().unwrap();
+        assert_eq!(row.len(), builds.len());
+        assert_eq!(row[1]["name"], "sharp xbow");
 
-        assert_eq!(dummy.first_turn.bonus_action.len(), 0);
-        assert_eq!(dummy.first_turn.expected_damage(12), d6);
-        assert_eq!(dummy.max_damage.name, "myturn");
+        let f = |v: &serde_json::Value| format!("{:.2}", v.as_f64().unwrap());
+        assert_eq!(f(&row[1]["favored_foe"]), "42.18");
+
+        let mark = row[1]["hunters_mark"].as_array().unwrap();
+        assert_eq!(mark.len(), 3);
+        assert_eq!(f(&mark[0]), "46.95");
+        assert_eq!(mark[1], 3);
+        assert_eq!(f(&mark[2]), "-8.30");
     }
 
     #[test]
-    fn test_turn_mark_calculations() {
-        let atk = Damage { dmg: d10, fixed: 5 };
-        let crit = Damage { dmg: d4, fixed: 3 };
-
-        let ac = 18;
-
-        let turn = Turn {
-            action: vec![
-                Attack {
-                    hit: 1,
-                    dmg: atk,
-                    crit: crit
-                };
-                3
-            ],
-            bonus_action: vec![
-                Attack {
-                    hit: 12,
-                    dmg: atk,
-                    crit: crit
-                };
-                2
-            ],
-            once_on_hit: Damage { dmg: d10, fixed: 4 },
+    fn test_render_table_no_ranger() {
+        let options = Options {
+            ranger: false,
             ..Default::default()
         };
+        let table = render_table(&default_builds(), 15..=15, &options);
+        let full = render_table(&default_builds(), 15..=15, &Options::default());
 
-        let mark = turn.mark();
-
-        let (max, rounds, deficit) = mark.breakeven(ac);
-
-        assert_eq!(mark.first_turn.bonus_action.len(), 0);
+        let lines: Vec<&str> = table.lines().collect();
 
         assert_eq!(
-            max.cmpable(),
-            (turn.clone()
-                + Damage {
-                    dmg: d6,
-                    ..Default::default()
-                })
-            .expected_damage(ac)
-            .cmpable()
+            lines[0],
+            " AC  |     xbow     |  sharp xbow  | sword/flurry "
         );
-
-        assert_eq!(rounds, 4);
-
-        assert_eq!(deficit.cmpable(), -1921);
+        assert_eq!(lines[1].len(), lines[0].len());
+        // plain damage, not Favored Foe
+        assert_eq!(
+            lines[2],
+            " 15  |        27.22 |      > 39.60 |        32.75"
+        );
+        assert!(lines[0].len() < full.lines().next().unwrap().len());
     }
 }